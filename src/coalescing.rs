@@ -0,0 +1,80 @@
+//! Types and traits for merging consecutive [`Operation`]s into one.
+//!
+//! [`Operation`]: crate::operation::Operation
+
+use crate::operation::Operation;
+
+/// The result of [`CoalescePolicy::coalesce()`].
+///
+/// `Ok` contains the merged operation. `Err` gives back both operations, unmerged and in their
+/// original order.
+pub type CoalesceResult = Result<Box<dyn Operation>, (Box<dyn Operation>, Box<dyn Operation>)>;
+
+/// A simple rule for grouping consecutive operations together, without writing a
+/// [`CoalescePolicy`].
+///
+/// This can be set on an [`UndoRedo`] through [`UndoRedo::set_coalesce_by()`].
+///
+/// [`UndoRedo`]: crate::undoredo::UndoRedo
+/// [`UndoRedo::set_coalesce_by()`]: crate::undoredo::UndoRedo::set_coalesce_by()
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub enum CoalesceKey {
+	/// Operations are never grouped by key.
+	#[default]
+	None,
+	/// Operations with the same [`Details::name`] are grouped.
+	///
+	/// [`Details::name`]: crate::operation::Details::name
+	SameName,
+	/// Operations sharing at least one of their [affected entities] are grouped.
+	///
+	/// [affected entities]: Operation::affected_entities()
+	SameEntity,
+	/// Operations with the same [transaction ID] are grouped. Operations without a transaction ID
+	/// are never grouped.
+	///
+	/// [transaction ID]: Operation::transaction_id()
+	SameTransactionId,
+}
+
+impl CoalesceKey {
+	/// Returns `true` if `next` should be grouped with `previous` under this key.
+	#[must_use]
+	pub fn matches(self, previous: &dyn Operation, next: &dyn Operation) -> bool {
+		match self {
+			Self::None => false,
+			Self::SameName => previous.details().name == next.details().name,
+			Self::SameEntity => {
+				let previous_entities = previous.affected_entities();
+				next.affected_entities()
+					.iter()
+					.any(|entity| previous_entities.contains(entity))
+			}
+			Self::SameTransactionId => previous
+				.transaction_id()
+				.is_some_and(|id| next.transaction_id() == Some(id)),
+		}
+	}
+}
+
+/// Decides whether and how two consecutive operations are merged into one.
+///
+/// A policy can be set on an [`UndoRedo`] through [`UndoRedo::set_coalesce_policy()`]. Whenever an
+/// operation is applied, the policy is given both the most-recently applied operation and the
+/// newly-applied one, and may merge them into a single undo step. This allows merge rules to live
+/// outside of individual operation types - for example, merging any two edits to the same entity's
+/// `Transform`, regardless of which operation made them.
+///
+/// [`UndoRedo`]: crate::undoredo::UndoRedo
+/// [`UndoRedo::set_coalesce_policy()`]: crate::undoredo::UndoRedo::set_coalesce_policy()
+pub trait CoalescePolicy: Send + Sync + 'static {
+	/// Attempts to merge `next` into `previous`.
+	///
+	/// Both operations have already been applied by the time this is called. As such, the merged
+	/// operation must not be applied again - it must only be able to undo (and later redo) the
+	/// effects of both operations, with `previous` applied first.
+	///
+	/// # Errors
+	/// Returns both operations, unmerged and in their original order, if they should not be merged.
+	fn coalesce(&self, previous: Box<dyn Operation>, next: Box<dyn Operation>) -> CoalesceResult;
+}
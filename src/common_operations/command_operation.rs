@@ -0,0 +1,116 @@
+//! An [`Operation`] which queues a Bevy [`Command`] when applied, and another when undone.
+use bevy_ecs::{system::Commands, world::Command};
+
+use crate::operation::{Details, Operation};
+
+/// A function which queues a command.
+type QueueFn = dyn Fn(&mut Commands) + Send + Sync;
+
+/// An [`Operation`] which queues a Bevy [`Command`] when applied, and another when undone.
+///
+/// This allows existing commands to be made undoable, by pairing each with a command which
+/// reverses it. As an operation may be redone any number of times, a fresh command is needed each
+/// time it is applied or undone. [`Self::new()`] clones the given commands for this, while
+/// [`Self::from_fns()`] calls a function to create each one.
+pub struct CommandOperation {
+	/// A descriptor for this operation.
+	details: Details,
+	/// Queues the command which applies this operation.
+	apply: Box<QueueFn>,
+	/// Queues the command which undoes this operation.
+	undo: Box<QueueFn>,
+}
+
+impl CommandOperation {
+	/// Creates a new `CommandOperation`, which queues a clone of `apply` when applied, and a clone
+	/// of `undo` when undone.
+	#[must_use]
+	pub fn new<A, U>(details: Details, apply: A, undo: U) -> Self
+	where
+		A: Command + Clone + Sync,
+		U: Command + Clone + Sync,
+	{
+		Self::from_fns(details, move || apply.clone(), move || undo.clone())
+	}
+
+	/// Creates a new `CommandOperation`, which queues the command returned by `apply` when
+	/// applied, and the command returned by `undo` when undone.
+	///
+	/// This is useful for commands which can't be cloned.
+	#[must_use]
+	pub fn from_fns<A, U>(
+		details: Details,
+		apply: impl Fn() -> A + Send + Sync + 'static,
+		undo: impl Fn() -> U + Send + Sync + 'static,
+	) -> Self
+	where
+		A: Command,
+		U: Command,
+	{
+		Self {
+			details,
+			apply: Box::new(move |commands| {
+				commands.queue(apply());
+			}),
+			undo: Box::new(move |commands| {
+				commands.queue(undo());
+			}),
+		}
+	}
+}
+
+impl Operation for CommandOperation {
+	fn details(&self) -> Details {
+		self.details.clone()
+	}
+
+	fn apply(&mut self, commands: &mut Commands) {
+		(self.apply)(commands);
+	}
+
+	fn undo(&self, commands: &mut Commands) {
+		(self.undo)(commands);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{test_fixtures::Counter, UndoRedo};
+	use bevy_ecs::world::World;
+	use rstest::rstest;
+
+	/// A command which adds to [`Counter`].
+	#[derive(Clone)]
+	struct AddToCounterCommand(i32);
+
+	impl Command for AddToCounterCommand {
+		fn apply(self, world: &mut World) {
+			world.resource_mut::<Counter>().0 += self.0;
+		}
+	}
+
+	#[rstest]
+	#[case::cloned(CommandOperation::new(Details::default(), AddToCounterCommand(2), AddToCounterCommand(-2)))]
+	#[case::from_fns(CommandOperation::from_fns(
+		Details::default(),
+		|| |world: &mut World| world.resource_mut::<Counter>().0 += 2,
+		|| |world: &mut World| world.resource_mut::<Counter>().0 -= 2,
+	))]
+	fn wrapped_commands_apply_and_undo(#[case] operation: CommandOperation) {
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		let mut undoredo = UndoRedo::new();
+
+		undoredo
+			.push_and_apply_world(operation, &mut world)
+			.unwrap();
+		assert_eq!(world.resource::<Counter>().0, 2);
+
+		undoredo.undo_world(&mut world).unwrap();
+		assert_eq!(world.resource::<Counter>().0, 0);
+
+		undoredo.redo_world(&mut world).unwrap();
+		assert_eq!(world.resource::<Counter>().0, 2);
+	}
+}
@@ -0,0 +1,96 @@
+//! An [`Operation`] which inserts a [`Resource`] into the World.
+use std::sync::{Arc, Mutex};
+
+use bevy_ecs::{
+	system::{Commands, Resource},
+	world::World,
+};
+
+use super::Slot;
+use crate::operation::Operation;
+
+/// An [`Operation`] which inserts a [`Resource`] into the World.
+///
+/// If the resource already exists, it is replaced, and undoing this operation restores the
+/// replaced value. Otherwise, undoing this operation removes the resource again.
+pub struct InsertResource<R: Resource> {
+	/// The value to insert. This is `None` while the value is in the World.
+	value: Slot<R>,
+	/// The value that was replaced when this operation was applied, if any.
+	previous: Slot<R>,
+}
+
+impl<R: Resource> InsertResource<R> {
+	/// Creates a new `InsertResource`, which will insert `value` when applied.
+	#[must_use]
+	pub fn new(value: R) -> Self {
+		Self {
+			value: Arc::new(Mutex::new(Some(value))),
+			previous: Slot::default(),
+		}
+	}
+}
+
+impl<R: Resource> Operation for InsertResource<R> {
+	fn apply(&mut self, commands: &mut Commands) {
+		let value = Slot::clone(&self.value);
+		let previous = Slot::clone(&self.previous);
+		commands.queue(move |world: &mut World| {
+			let Some(value) = super::lock(&value).take() else {
+				return;
+			};
+			*super::lock(&previous) = world.remove_resource::<R>();
+			world.insert_resource(value);
+		});
+	}
+
+	fn undo(&self, commands: &mut Commands) {
+		let value = Slot::clone(&self.value);
+		let previous = Slot::clone(&self.previous);
+		commands.queue(move |world: &mut World| {
+			// Take back the value we inserted, so that it can be inserted again when redoing.
+			let mut value = super::lock(&value);
+			if value.is_none() {
+				*value = world.remove_resource::<R>();
+			}
+
+			if let Some(previous) = super::lock(&previous).take() {
+				world.insert_resource(previous);
+			}
+		});
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::undoredo::with_world_commands;
+	use rstest::rstest;
+
+	#[derive(Debug, PartialEq, Resource)]
+	struct Score(u32);
+
+	#[rstest]
+	#[case::new_resource(None)]
+	#[case::replacing_resource(Some(Score(1)))]
+	fn undo_restores_prior_state(#[case] prior: Option<Score>) {
+		let mut world = World::new();
+		let expected_prior = prior.as_ref().map(|score| score.0);
+		if let Some(prior) = prior {
+			world.insert_resource(prior);
+		}
+
+		let mut operation = InsertResource::new(Score(2));
+		with_world_commands(&mut world, |commands| operation.apply(commands));
+		assert_eq!(world.get_resource::<Score>(), Some(&Score(2)));
+
+		with_world_commands(&mut world, |commands| operation.undo(commands));
+		assert_eq!(
+			world.get_resource::<Score>().map(|score| score.0),
+			expected_prior
+		);
+
+		with_world_commands(&mut world, |commands| operation.apply(commands));
+		assert_eq!(world.get_resource::<Score>(), Some(&Score(2)));
+	}
+}
@@ -1,6 +1,34 @@
 //! A collection of common [`Operation`]s that may be useful in any Bevy app.
 //!
 //! [`Operation`]: crate::operation::Operation
+mod command_operation;
+mod insert_resource;
 mod operation_group;
+mod placeholder;
+mod remove_resource;
+#[cfg(feature = "scene")]
+mod world_snapshot;
 
-pub use self::operation_group::OperationGroup;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+pub use self::{
+	command_operation::CommandOperation, insert_resource::InsertResource,
+	operation_group::OperationGroup, placeholder::PlaceholderOperation,
+	remove_resource::RemoveResource,
+};
+
+#[cfg(feature = "scene")]
+pub use self::world_snapshot::WorldSnapshot;
+
+/// A shared slot holding a value that moves between an operation and the World.
+///
+/// Operations only queue commands, so any value taken out of the World (such as a resource being
+/// replaced) is only available once those commands run. Both the operation and its commands hold
+/// a handle to the same slot, so that the operation can later put the value back.
+type Slot<T> = Arc<Mutex<Option<T>>>;
+
+/// Locks `slot`, ignoring any poisoning. The slot's contents are always left in a valid state, so a
+/// panic while it was locked doesn't make them unusable.
+fn lock<T>(slot: &Slot<T>) -> MutexGuard<'_, Option<T>> {
+	slot.lock().unwrap_or_else(PoisonError::into_inner)
+}
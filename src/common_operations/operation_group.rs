@@ -1,10 +1,14 @@
 //! A collection of [`Operation`]s, used to group them together and treat them as one operation.
 use bevy_ecs::{
+	entity::Entity,
 	system::Commands,
 	world::{Command, CommandQueue, World},
 };
 
-use crate::operation::{Details, Operation};
+use crate::{
+	error::Error,
+	operation::{Details, Operation, OperationContext},
+};
 
 /// A collection of [`Operation`]s, used to group them together and treat them as one operation.
 pub struct OperationGroup {
@@ -27,7 +31,62 @@ impl OperationGroup {
 	/// Pushes an operation into this group. Operations will be applied in the order they were
 	/// pushed, and undone in reverse order.
 	pub fn push<O: Operation>(&mut self, operation: O) {
-		self.op_list.push(Box::new(operation));
+		self.push_boxed(Box::new(operation));
+	}
+
+	/// Returns `true` if this group contains no operations.
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.op_list.is_empty()
+	}
+
+	/// Pushes an already-boxed operation into this group. See [`Self::push()`] for more details.
+	pub fn push_boxed(&mut self, operation: Box<dyn Operation>) {
+		self.op_list.push(operation);
+	}
+
+	/// Returns an iterator over the operations in this group, in the order they are applied.
+	pub fn operations(&self) -> impl DoubleEndedIterator<Item = &dyn Operation> + '_ {
+		self.op_list.iter().map(AsRef::as_ref)
+	}
+}
+
+impl Default for OperationGroup {
+	/// Creates a new, empty [`OperationGroup`] named "Group".
+	///
+	/// This is useful where a group's name isn't meaningful, such as when grouping everything
+	/// applied in a frame. Use [`Self::new()`] to give the group a name.
+	fn default() -> Self {
+		Self::new(Details {
+			name: "Group".to_owned(),
+		})
+	}
+}
+
+impl Extend<Box<dyn Operation>> for OperationGroup {
+	/// Pushes each operation in `iter` into this group, as with [`Self::push_boxed()`].
+	fn extend<I: IntoIterator<Item = Box<dyn Operation>>>(&mut self, iter: I) {
+		self.op_list.extend(iter);
+	}
+}
+
+impl FromIterator<Box<dyn Operation>> for OperationGroup {
+	/// Creates a new group containing the operations in `iter`. The group's details are the same
+	/// as those of [`Self::default()`].
+	fn from_iter<I: IntoIterator<Item = Box<dyn Operation>>>(iter: I) -> Self {
+		let mut group = Self::default();
+		group.extend(iter);
+		group
+	}
+}
+
+impl IntoIterator for OperationGroup {
+	type Item = Box<dyn Operation>;
+	type IntoIter = std::vec::IntoIter<Box<dyn Operation>>;
+
+	/// Returns an iterator over the operations in this group, in the order they are applied.
+	fn into_iter(self) -> Self::IntoIter {
+		self.op_list.into_iter()
 	}
 }
 
@@ -47,12 +106,58 @@ impl Operation for OperationGroup {
 		self.details.clone()
 	}
 
+	/// Returns the total weight of the operations in this group.
+	fn weight(&self) -> usize {
+		self.op_list.iter().map(|op| op.weight()).sum()
+	}
+
+	/// Returns `true` if every operation in this group may be undone without asking the user
+	/// first.
+	fn confirm_undo(&self) -> bool {
+		self.op_list.iter().all(|op| op.confirm_undo())
+	}
+
+	/// Returns the entities affected by each operation in this group, in order.
+	fn affected_entities(&self) -> Vec<Entity> {
+		self.op_list
+			.iter()
+			.flat_map(|op| op.affected_entities())
+			.collect()
+	}
+
 	fn apply(&mut self, commands: &mut Commands) {
-		for op in &mut self.op_list {
-			op.apply(commands);
+		let _ = self.apply_in_context(commands, &mut OperationContext::new());
+	}
+
+	/// Applies each operation in this group in order, stopping at the first one which fails.
+	///
+	/// If an operation fails, the operations before it are undone in reverse order, such that the
+	/// commands queued by this group have no overall effect.
+	fn apply_in_context(
+		&mut self,
+		commands: &mut Commands,
+		context: &mut OperationContext,
+	) -> Result<(), Error> {
+		let failure = self.op_list.iter_mut().enumerate().find_map(|(index, op)| {
+			op.apply_in_context(commands, context)
+				.err()
+				.map(|error| (index, error))
+		});
+		let Some((failed_index, error)) = failure else {
+			return Ok(());
+		};
+
+		for op in self.op_list[..failed_index].iter().rev() {
+			op.undo(commands);
 		}
+		Err(error)
 	}
 
+	/// Undoes each operation in this group in reverse order.
+	///
+	/// Each operation's commands are queued one after another, with nothing else queued in
+	/// between, so they are applied as one contiguous, strictly-reversed run. Any commands those
+	/// commands queue in turn are applied before the next operation's commands.
 	fn undo(&self, commands: &mut Commands) {
 		let reversed_op_list = self.op_list.iter().rev();
 		for op in reversed_op_list {
@@ -60,3 +165,104 @@ impl Operation for OperationGroup {
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_fixtures::{AddToCounter, Counter};
+	use bevy_ecs::system::Resource;
+	use rstest::rstest;
+
+	#[derive(Default, Resource)]
+	struct Log(Vec<String>);
+
+	/// Logs its name when undone. If `nested`, the name is logged by a command queued from within
+	/// another command.
+	struct Logged {
+		name: &'static str,
+		nested: bool,
+	}
+
+	impl Operation for Logged {
+		fn apply(&mut self, _commands: &mut Commands) {}
+
+		fn undo(&self, commands: &mut Commands) {
+			let (name, nested) = (self.name, self.nested);
+			commands.queue(move |world: &mut World| {
+				let log = move |world: &mut World| world.resource_mut::<Log>().0.push(name.into());
+				if nested {
+					world.commands().queue(log);
+				} else {
+					log(world);
+				}
+			});
+		}
+	}
+
+	#[rstest]
+	fn undo_is_strictly_reversed_among_surrounding_commands() {
+		let mut group = OperationGroup::default();
+		for (name, nested) in [("a", false), ("b", true), ("c", false)] {
+			group.push(Logged { name, nested });
+		}
+
+		let mut world = World::new();
+		world.init_resource::<Log>();
+		let mut command_queue = CommandQueue::default();
+		let mut commands = Commands::new(&mut command_queue, &world);
+		let log = |name: &'static str| {
+			move |world: &mut World| world.resource_mut::<Log>().0.push(name.into())
+		};
+		commands.queue(log("before"));
+		group.undo(&mut commands);
+		commands.queue(log("after"));
+		command_queue.apply(&mut world);
+
+		assert_eq!(
+			world.resource::<Log>().0,
+			["before", "c", "b", "a", "after"]
+		);
+	}
+
+	#[rstest]
+	fn default_group_is_named_and_applies() {
+		let mut group = OperationGroup::default();
+		assert!(!group.details().name.is_empty());
+		group.push(AddToCounter(1));
+		group.push(AddToCounter(1));
+
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		let mut command_queue = CommandQueue::default();
+		let mut commands = Commands::new(&mut command_queue, &world);
+		Operation::apply(&mut group, &mut commands);
+		command_queue.apply(&mut world);
+		assert_eq!(world.resource::<Counter>().0, 2);
+
+		let mut commands = Commands::new(&mut command_queue, &world);
+		group.undo(&mut commands);
+		command_queue.apply(&mut world);
+		assert_eq!(world.resource::<Counter>().0, 0);
+	}
+
+	#[rstest]
+	fn operations_can_be_collected_and_iterated() {
+		let mut group: OperationGroup = ["a", "b"]
+			.into_iter()
+			.map(|name| {
+				Box::new(Logged {
+					name,
+					nested: false,
+				}) as Box<dyn Operation>
+			})
+			.collect();
+		assert_eq!(group.details(), OperationGroup::default().details());
+
+		group.extend([Box::new(AddToCounter(1)) as Box<dyn Operation>]);
+		let names: Vec<_> = group
+			.into_iter()
+			.map(|operation| operation.downcast_ref::<Logged>().map(|logged| logged.name))
+			.collect();
+		assert_eq!(names, [Some("a"), Some("b"), None]);
+	}
+}
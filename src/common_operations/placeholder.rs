@@ -0,0 +1,50 @@
+//! An [`Operation`] which stands in for an operation that could not be saved.
+use bevy_ecs::system::Commands;
+
+use crate::{
+	error::Error,
+	operation::{Details, Operation, OperationContext},
+};
+
+/// An [`Operation`] which stands in for an operation that could not be saved, such as one which
+/// does not support reflection. Only the original operation's [`Details`] are kept.
+///
+/// Placeholders allow a history to be displayed after being loaded, even if some of its operations
+/// were lost. However, a placeholder can't do anything the original operation did - applying it
+/// (and so redoing it) always fails, and undoing it does nothing.
+pub struct PlaceholderOperation {
+	/// The details of the operation this stands in for.
+	details: Details,
+}
+
+impl PlaceholderOperation {
+	/// Creates a new `PlaceholderOperation`, standing in for an operation with the given details.
+	#[must_use]
+	pub const fn new(details: Details) -> Self {
+		Self { details }
+	}
+}
+
+impl Operation for PlaceholderOperation {
+	fn details(&self) -> Details {
+		self.details.clone()
+	}
+
+	fn apply(&mut self, commands: &mut Commands) {
+		let _ = self.apply_in_context(commands, &mut OperationContext::new());
+	}
+
+	/// Always fails, as the original operation's data was not kept.
+	fn apply_in_context(
+		&mut self,
+		_commands: &mut Commands,
+		_context: &mut OperationContext,
+	) -> Result<(), Error> {
+		Err(Error::OperationFailed(format!(
+			"`{}` is a placeholder, and cannot be applied",
+			self.details.name
+		)))
+	}
+
+	fn undo(&self, _commands: &mut Commands) {}
+}
@@ -0,0 +1,90 @@
+//! An [`Operation`] which removes a [`Resource`] from the World.
+use core::marker::PhantomData;
+
+use bevy_ecs::{
+	system::{Commands, Resource},
+	world::World,
+};
+
+use super::Slot;
+use crate::operation::Operation;
+
+/// An [`Operation`] which removes a [`Resource`] from the World.
+///
+/// Undoing this operation reinserts the removed value. If the resource did not exist when this
+/// operation was applied, both applying and undoing it do nothing.
+pub struct RemoveResource<R: Resource> {
+	/// The value that was removed when this operation was applied, if any.
+	removed: Slot<R>,
+	/// Ties this operation to the resource type it removes.
+	_marker: PhantomData<fn() -> R>,
+}
+
+impl<R: Resource> RemoveResource<R> {
+	/// Creates a new `RemoveResource`.
+	#[must_use]
+	pub fn new() -> Self {
+		Self {
+			removed: Slot::default(),
+			_marker: PhantomData,
+		}
+	}
+}
+
+// Manually impl Default, to avoid putting a `Default` bound on R.
+impl<R: Resource> Default for RemoveResource<R> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<R: Resource> Operation for RemoveResource<R> {
+	fn apply(&mut self, commands: &mut Commands) {
+		let removed = Slot::clone(&self.removed);
+		commands.queue(move |world: &mut World| {
+			*super::lock(&removed) = world.remove_resource::<R>();
+		});
+	}
+
+	fn undo(&self, commands: &mut Commands) {
+		let removed = Slot::clone(&self.removed);
+		commands.queue(move |world: &mut World| {
+			if let Some(removed) = super::lock(&removed).take() {
+				world.insert_resource(removed);
+			}
+		});
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::undoredo::with_world_commands;
+	use rstest::rstest;
+
+	#[derive(Debug, PartialEq, Resource)]
+	struct Score(u32);
+
+	#[rstest]
+	fn undo_restores_removed_resource() {
+		let mut world = World::new();
+		world.insert_resource(Score(1));
+
+		let mut operation = RemoveResource::<Score>::new();
+		with_world_commands(&mut world, |commands| operation.apply(commands));
+		assert_eq!(world.get_resource::<Score>(), None);
+
+		with_world_commands(&mut world, |commands| operation.undo(commands));
+		assert_eq!(world.get_resource::<Score>(), Some(&Score(1)));
+	}
+
+	#[rstest]
+	fn missing_resource_is_left_missing() {
+		let mut world = World::new();
+
+		let mut operation = RemoveResource::<Score>::new();
+		with_world_commands(&mut world, |commands| operation.apply(commands));
+		with_world_commands(&mut world, |commands| operation.undo(commands));
+		assert_eq!(world.get_resource::<Score>(), None);
+	}
+}
@@ -0,0 +1,165 @@
+//! An [`Operation`] which captures a set of entities when applied, and restores them when undone.
+use bevy_ecs::{
+	entity::{Entity, EntityHashMap},
+	reflect::{AppTypeRegistry, ReflectComponent},
+	system::Commands,
+	world::World,
+};
+use bevy_scene::{DynamicScene, DynamicSceneBuilder};
+
+use super::Slot;
+use crate::operation::Operation;
+
+/// An [`Operation`] which captures a set of entities when applied, and restores them when undone.
+///
+/// This is the "nuclear option" for operations which are too complex to invert by hand. Place a
+/// `WorldSnapshot` of every entity an operation may touch before that operation in an
+/// [`OperationGroup`], and undoing the group will put those entities back the way they were.
+///
+/// The entities are captured into a [`DynamicScene`] when the commands queued by this operation
+/// are applied. Only components which are registered in the World's [`AppTypeRegistry`] with
+/// `#[reflect(Component)]` are captured and restored; any other components are left untouched.
+/// When undone, captured entities which still exist have their registered components replaced by
+/// the captured ones, while captured entities which have since been despawned are spawned again
+/// (under a new `Entity`).
+///
+/// # Cost
+/// Every captured component is cloned through reflection each time this operation is applied,
+/// and the snapshot is kept in memory for as long as this operation is in the history. Restoring
+/// is similarly expensive. Prefer operations which invert themselves directly where possible, and
+/// keep the set of captured entities small.
+///
+/// # Panics
+/// The commands queued by this operation will panic if the World has no [`AppTypeRegistry`]
+/// resource.
+///
+/// [`OperationGroup`]: super::OperationGroup
+pub struct WorldSnapshot {
+	/// The entities to capture.
+	entities: Vec<Entity>,
+	/// The captured entities. This is `None` until this operation's commands have been applied.
+	scene: Slot<DynamicScene>,
+}
+
+impl WorldSnapshot {
+	/// Creates a new `WorldSnapshot`, which will capture `entities` when applied.
+	#[must_use]
+	pub fn new(entities: impl IntoIterator<Item = Entity>) -> Self {
+		Self {
+			entities: entities.into_iter().collect(),
+			scene: Slot::default(),
+		}
+	}
+}
+
+impl Operation for WorldSnapshot {
+	fn affected_entities(&self) -> Vec<Entity> {
+		self.entities.clone()
+	}
+
+	fn apply(&mut self, commands: &mut Commands) {
+		let entities = self.entities.clone();
+		let scene = Slot::clone(&self.scene);
+		commands.queue(move |world: &mut World| {
+			let captured = DynamicSceneBuilder::from_world(world)
+				.extract_entities(entities.into_iter())
+				.build();
+			*super::lock(&scene) = Some(captured);
+		});
+	}
+
+	fn undo(&self, commands: &mut Commands) {
+		let scene = Slot::clone(&self.scene);
+		commands.queue(move |world: &mut World| {
+			let Some(scene) = super::lock(&scene).take() else {
+				return;
+			};
+
+			// Map each captured entity that still exists onto itself, clearing out its registered
+			// components so that any added since the capture are removed. Captured entities which
+			// no longer exist are left out of the map, so that they're spawned again.
+			let mut entity_map = EntityHashMap::default();
+			for entity in scene
+				.entities
+				.iter()
+				.map(|scene_entity| scene_entity.entity)
+			{
+				if world.get_entity(entity).is_ok() {
+					self::remove_registered_components(world, entity);
+					entity_map.insert(entity, entity);
+				}
+			}
+
+			// Every component in the scene was extracted from this World, and so is registered.
+			let _ = scene.write_to_world(world, &mut entity_map);
+		});
+	}
+}
+
+/// Removes every component from `entity` which is registered in the World's [`AppTypeRegistry`]
+/// with `#[reflect(Component)]`.
+fn remove_registered_components(world: &mut World, entity: Entity) {
+	let registry = world.resource::<AppTypeRegistry>().clone();
+	let registry = registry.read();
+
+	let reflect_components: Vec<ReflectComponent> = world
+		.entity(entity)
+		.archetype()
+		.components()
+		.filter_map(|component_id| world.components().get_info(component_id)?.type_id())
+		.filter_map(|type_id| registry.get_type_data::<ReflectComponent>(type_id))
+		.cloned()
+		.collect();
+
+	let mut entity_mut = world.entity_mut(entity);
+	for reflect_component in reflect_components {
+		reflect_component.remove(&mut entity_mut);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::undoredo::with_world_commands;
+	use bevy_ecs::{component::Component, reflect::ReflectComponent};
+	use bevy_reflect::Reflect;
+	use rstest::rstest;
+
+	#[derive(Clone, Copy, Debug, PartialEq, Component, Reflect)]
+	#[reflect(Component)]
+	struct Health(u32);
+
+	#[derive(Clone, Copy, Debug, PartialEq, Component, Reflect)]
+	#[reflect(Component)]
+	struct Poisoned;
+
+	#[rstest]
+	fn undo_restores_captured_entities() {
+		let mut world = World::new();
+		world.init_resource::<AppTypeRegistry>();
+		{
+			let registry = world.resource::<AppTypeRegistry>();
+			let mut registry = registry.write();
+			registry.register::<Health>();
+			registry.register::<Poisoned>();
+		}
+
+		let a = world.spawn(Health(10)).id();
+		let b = world.spawn(Health(20)).id();
+		let untouched = world.spawn(Health(30)).id();
+
+		let mut snapshot = WorldSnapshot::new([a, b]);
+		with_world_commands(&mut world, |commands| snapshot.apply(commands));
+
+		world.entity_mut(a).insert((Health(1), Poisoned));
+		world.entity_mut(b).remove::<Health>();
+		world.entity_mut(untouched).insert(Health(3));
+
+		with_world_commands(&mut world, |commands| snapshot.undo(commands));
+
+		assert_eq!(world.get::<Health>(a), Some(&Health(10)));
+		assert_eq!(world.get::<Poisoned>(a), None);
+		assert_eq!(world.get::<Health>(b), Some(&Health(20)));
+		assert_eq!(world.get::<Health>(untouched), Some(&Health(3)));
+	}
+}
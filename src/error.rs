@@ -2,13 +2,21 @@
 use core::fmt::{Display, Formatter, Result as FmtResult};
 
 /// The error type for history-type operations.
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 #[non_exhaustive]
 pub enum Error {
 	/// There is no applicable history available for this operation.
 	NoApplicableHistory,
 	/// There is no queued operation available to apply.
 	NoQueuedOperations,
+	/// An operation could not be applied. Contains a description of why.
+	OperationFailed(String),
+	/// An operation failed validation, and so was rejected. Contains a description of why.
+	InvalidOperation(String),
+	/// There is no gesture in progress to add to or end.
+	NoActiveGesture,
+	/// The operation to be undone asks for the user to confirm the undo first.
+	ConfirmationRequired,
 }
 
 impl Display for Error {
@@ -18,8 +26,40 @@ impl Display for Error {
 				"No applicable history available to perform this operation"
 			}
 			Self::NoQueuedOperations => "No operation available to apply",
+			Self::NoActiveGesture => "No gesture is in progress",
+			Self::ConfirmationRequired => "This operation must be confirmed before it is undone",
+			Self::OperationFailed(reason) => {
+				return write!(f, "Operation failed to apply: {reason}");
+			}
+			Self::InvalidOperation(reason) => {
+				return write!(f, "Operation is invalid: {reason}");
+			}
 		};
 
 		write!(f, "{msg}")
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use rstest::rstest;
+
+	#[rstest]
+	#[case::unit_variants(Error::NoQueuedOperations, Error::NoQueuedOperations, true)]
+	#[case::different_variants(Error::NoQueuedOperations, Error::NoApplicableHistory, false)]
+	#[case::same_reason(
+		Error::InvalidOperation("no target".to_owned()),
+		Error::InvalidOperation("no target".to_owned()),
+		true
+	)]
+	#[case::different_reason(
+		Error::OperationFailed("no target".to_owned()),
+		Error::OperationFailed("despawned".to_owned()),
+		false
+	)]
+	fn errors_compare_by_value(#[case] a: Error, #[case] b: Error, #[case] equal: bool) {
+		assert_eq!(a == b, equal);
+		assert_eq!(a.clone(), a);
+	}
+}
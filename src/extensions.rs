@@ -1,6 +1,7 @@
 //! Extensions for various Bevy types, to make using undo/redo functionality more friendly.
 
 use bevy_ecs::{
+	change_detection::DetectChangesMut,
 	system::Commands,
 	world::{Command, Mut, World},
 };
@@ -21,6 +22,17 @@ pub trait CommandsUndoRedoExt {
 	/// # Panics
 	/// The command will panic if no [`UndoRedo`] resource has been inserted.
 	fn redo(&mut self);
+	/// Pushes a [`Command`] to the queue for applying all queued operations in the world's
+	/// [`UndoRedo`] resource.
+	///
+	/// This allows a system to request that the queue be applied at a precise point, rather than
+	/// waiting for [`apply_queued_operations`] to run.
+	///
+	/// # Panics
+	/// The command will panic if no [`UndoRedo`] resource has been inserted.
+	///
+	/// [`apply_queued_operations`]: crate::undoredo::apply_queued_operations
+	fn apply_undo_queue(&mut self);
 }
 
 impl CommandsUndoRedoExt for Commands<'_, '_> {
@@ -31,11 +43,19 @@ impl CommandsUndoRedoExt for Commands<'_, '_> {
 	fn redo(&mut self) {
 		self.queue(PerformRedo);
 	}
+
+	fn apply_undo_queue(&mut self) {
+		self.queue(ApplyUndoQueue);
+	}
 }
 
 /// Grabs the `UndoRedo` resource from the world, creates a `Commands`, and then calls a given
 /// closure with both.
 ///
+/// The resource is only marked as changed if the closure may have changed it - that is, unless it
+/// returned [`HistoryError::NoApplicableHistory`], [`HistoryError::NoQueuedOperations`], or
+/// [`HistoryError::ConfirmationRequired`], which are only returned when nothing was done.
+///
 /// # Panics
 /// Panics if no [`UndoRedo`] resource has been inserted.
 fn use_undoredo_with_commands(
@@ -47,7 +67,18 @@ fn use_undoredo_with_commands(
 	// Rust's aliasing rules.
 	world.resource_scope(|world, mut undoredo: Mut<UndoRedo>| {
 		let mut commands = world.commands();
-		f(&mut undoredo, &mut commands)
+		let result = f(undoredo.bypass_change_detection(), &mut commands);
+
+		if !matches!(
+			result,
+			Err(HistoryError::NoApplicableHistory
+				| HistoryError::NoQueuedOperations
+				| HistoryError::ConfirmationRequired)
+		) {
+			undoredo.set_changed();
+		}
+
+		result
 	})
 }
 
@@ -68,3 +99,36 @@ impl Command for PerformRedo {
 		let _ = self::use_undoredo_with_commands(world, UndoRedo::redo);
 	}
 }
+
+/// Command that applies all queued operations in the world's [`UndoRedo`] resource.
+pub struct ApplyUndoQueue;
+
+impl Command for ApplyUndoQueue {
+	fn apply(self, world: &mut World) {
+		let _ = self::use_undoredo_with_commands(world, UndoRedo::apply_queue);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_fixtures::{AddToCounter, Counter};
+	use bevy_ecs::system::RunSystemOnce;
+	use rstest::rstest;
+
+	#[rstest]
+	fn apply_undo_queue_applies_once_flushed() {
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		world.init_resource::<UndoRedo>();
+		let mut undoredo = world.resource_mut::<UndoRedo>();
+		undoredo.push_to_queue(AddToCounter(1)).unwrap();
+		undoredo.push_to_queue(AddToCounter(1)).unwrap();
+
+		world
+			.run_system_once(|mut commands: Commands| commands.apply_undo_queue())
+			.unwrap();
+		assert_eq!(world.resource::<Counter>().0, 2);
+		assert!(world.resource::<UndoRedo>().can_undo());
+	}
+}
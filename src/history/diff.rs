@@ -0,0 +1,29 @@
+//! Types for comparing two instances of [`History`].
+//!
+//! [`History`]: super::History
+
+/// The differences between the committed items of two histories, as returned by
+/// [`History::diff()`].
+///
+/// As histories are mostly appended to, this is a simple prefix-based comparison: both histories
+/// share some number of committed items at the start, after which the newer history's remaining
+/// items were added, and the older history's remaining items were removed.
+///
+/// [`History::diff()`]: super::History::diff()
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diff<'a, T> {
+	/// The number of committed items at the start of both histories which are equal.
+	pub common_len: usize,
+	/// Committed items that are only in the newer history, from oldest to newest.
+	pub added: Vec<&'a T>,
+	/// Committed items that are only in the older history, from oldest to newest.
+	pub removed: Vec<&'a T>,
+}
+
+impl<T> Diff<'_, T> {
+	/// Returns `true` if the two histories' committed items are the same.
+	#[must_use]
+	pub const fn is_empty(&self) -> bool {
+		self.added.is_empty() && self.removed.is_empty()
+	}
+}
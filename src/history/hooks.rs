@@ -0,0 +1,114 @@
+//! Callbacks which can be registered on a [`History`].
+//!
+//! [`History`]: super::History
+
+use core::{
+	cmp::Ordering,
+	fmt::{Debug, Formatter, Result as FmtResult},
+	hash::{Hash, Hasher},
+};
+
+/// A callback which is given a reference to an item in a [`History`].
+///
+/// [`History`]: super::History
+pub type Hook<T> = Box<dyn FnMut(&T) + Send + Sync>;
+
+/// A callback which is given the number of committed items in a [`History`], called when that
+/// number reaches the history's [`warn_threshold`].
+///
+/// [`History`]: super::History
+/// [`warn_threshold`]: super::History::warn_threshold
+pub type ThresholdHook = Box<dyn FnMut(usize) + Send + Sync>;
+
+/// The set of callbacks registered on a [`History`].
+///
+/// Hooks are not considered part of a history's contents. As such, they are ignored when comparing
+/// or hashing histories, and are not carried over when a history is cloned.
+///
+/// [`History`]: super::History
+pub(super) struct Hooks<T> {
+	/// Called whenever an item is pushed to the history.
+	pub(super) on_push: Option<Hook<T>>,
+	/// Called whenever an item is evicted from the history.
+	pub(super) on_evict: Option<Hook<T>>,
+	/// Called whenever the number of committed items reaches the warning threshold.
+	pub(super) on_warn_threshold: Option<ThresholdHook>,
+}
+
+impl<T> Hooks<T> {
+	/// Creates a new set of hooks, with no callbacks registered.
+	pub(super) const fn new() -> Self {
+		Self {
+			on_push: None,
+			on_evict: None,
+			on_warn_threshold: None,
+		}
+	}
+
+	/// Calls the `on_push` hook with `item`, if one is registered.
+	pub(super) fn call_on_push(&mut self, item: &T) {
+		if let Some(on_push) = &mut self.on_push {
+			on_push(item);
+		}
+	}
+
+	/// Calls the `on_evict` hook with `item`, if one is registered.
+	pub(super) fn call_on_evict(&mut self, item: &T) {
+		if let Some(on_evict) = &mut self.on_evict {
+			on_evict(item);
+		}
+	}
+
+	/// Calls the `on_warn_threshold` hook with `len`, if one is registered.
+	pub(super) fn call_on_warn_threshold(&mut self, len: usize) {
+		if let Some(on_warn_threshold) = &mut self.on_warn_threshold {
+			on_warn_threshold(len);
+		}
+	}
+}
+
+impl<T> Clone for Hooks<T> {
+	fn clone(&self) -> Self {
+		Self::new()
+	}
+}
+
+impl<T> Debug for Hooks<T> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+		f.debug_struct("Hooks")
+			.field("on_push", &self.on_push.is_some())
+			.field("on_evict", &self.on_evict.is_some())
+			.field("on_warn_threshold", &self.on_warn_threshold.is_some())
+			.finish()
+	}
+}
+
+impl<T> Default for Hooks<T> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<T> Hash for Hooks<T> {
+	fn hash<H: Hasher>(&self, _state: &mut H) {}
+}
+
+impl<T> PartialEq for Hooks<T> {
+	fn eq(&self, _other: &Self) -> bool {
+		true
+	}
+}
+
+impl<T> Eq for Hooks<T> {}
+
+impl<T> PartialOrd for Hooks<T> {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl<T> Ord for Hooks<T> {
+	fn cmp(&self, _other: &Self) -> Ordering {
+		Ordering::Equal
+	}
+}
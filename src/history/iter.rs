@@ -131,6 +131,22 @@ impl<'a, T> Iterator for UndoneIter<'a, T> {
 	}
 }
 
+/// An item of a History, along with its position in the history's timeline.
+///
+/// This is returned by [`History::iter_timeline()`](super::History::iter_timeline()).
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct TimelineEntry<T> {
+	/// The item itself.
+	pub item: T,
+	/// The item's index within the timeline, as in [`History::iter()`](super::History::iter()).
+	pub index: usize,
+	/// Whether the item is committed, rather than undone.
+	pub committed: bool,
+	/// Whether the item is the newest committed item - that is, whether the history's cursor sits
+	/// directly after it. At most one item in a timeline is current.
+	pub is_current: bool,
+}
+
 #[cfg(test)]
 mod tests {
 	use super::super::History;
@@ -1,13 +1,34 @@
 //! Types related to [`History`], a collection which represents the history of something.
+mod diff;
+mod hooks;
 mod iter;
+#[cfg(test)]
+mod properties;
+#[cfg(feature = "reflect")]
+mod reflect;
+mod size;
 
-use core::num::NonZeroUsize;
+use core::{
+	cmp::Ordering,
+	hash::{Hash, Hasher},
+	iter::Rev,
+	mem,
+	num::NonZeroUsize,
+	ops::Range,
+};
 
 use std::collections::VecDeque;
 
 use crate::error::Error;
 
-pub use self::iter::{CommittedIter, Iter, UndoneIter};
+use self::hooks::Hooks;
+
+pub use self::{
+	diff::Diff,
+	hooks::{Hook, ThresholdHook},
+	iter::{CommittedIter, Iter, TimelineEntry, UndoneIter},
+	size::HeapSize,
+};
 
 /// A collection which holds a set of items that represents the history of something, and acts as a
 /// cursor into that set of items.
@@ -19,7 +40,7 @@ pub use self::iter::{CommittedIter, Iter, UndoneIter};
 /// [`World`]: bevy_ecs::world::World
 // TODO List:
 // * `get()`, `get_mut()`
-// * `get_limit()`, `set_limit()`
+// * `get_limit()`
 // * `impl<T> IntoIterator for History<T>`
 //   * Plus `iter()`, `iter_committed()`, `iter_undone()`
 #[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
@@ -36,6 +57,34 @@ pub struct History<T> {
 	/// The maximum length of this history. Any committed items past this limit will be
 	/// automatically culled the next time an item is pushed.
 	pub limit: Option<NonZeroUsize>,
+	/// A soft limit on the length of this history. Unlike [`Self::limit`], nothing is removed when
+	/// this is reached; instead, the callback registered through [`Self::set_on_warn_threshold()`]
+	/// is called. This allows an app to (for example) suggest saving or clearing the history.
+	pub warn_threshold: Option<NonZeroUsize>,
+	/// Whether pushed items are stored.
+	pub mode: HistoryMode,
+	/// Callbacks registered on this history.
+	hooks: Hooks<T>,
+	/// Bookkeeping used to work out which items [`Self::take_new_since()`] should return.
+	save_progress: SaveProgress,
+}
+
+/// Whether a [`History`] stores the items pushed to it.
+#[expect(
+	clippy::module_name_repetitions,
+	reason = "This is the mode of a history, and is named as such."
+)]
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HistoryMode {
+	/// Pushed items are stored as committed items, as normal.
+	#[default]
+	Recording,
+	/// Pushed items are dropped rather than stored. Pushing still clears the undone list, as
+	/// redoing an item on top of one that wasn't stored would not make sense.
+	///
+	/// This is useful for applying changes without keeping any history, without needing to special
+	/// case the code doing so. Items which were already stored are left untouched.
+	Disabled,
 }
 
 impl<T> History<T> {
@@ -46,17 +95,102 @@ impl<T> History<T> {
 			committed: VecDeque::new(),
 			undone: Vec::new(),
 			limit: None,
+			warn_threshold: None,
+			mode: HistoryMode::Recording,
+			hooks: Hooks::new(),
+			save_progress: SaveProgress::new(),
+		}
+	}
+
+	/// Creates a new `History` with its [limit] set to `limit`, and room for that many committed
+	/// items allocated up front.
+	///
+	/// This behaves the same as setting [limit] on a new history - pushing at the limit drops the
+	/// oldest item in constant time either way. The only difference is that the full capacity is
+	/// allocated here, so the committed list never has to reallocate as it fills up (unless its
+	/// capacity is shrunk, such as through [`Self::shrink_to_fit()`], or the limit is raised).
+	/// This suits operations produced at a high frequency, such as every frame.
+	///
+	/// [limit]: Self::limit
+	#[must_use = "History does not store anything on its own - you must push items for it to store."]
+	pub fn with_ring_limit(limit: NonZeroUsize) -> Self {
+		Self {
+			committed: VecDeque::with_capacity(limit.get()),
+			limit: Some(limit),
+			..Self::new()
+		}
+	}
+
+	/// Consumes the history, calling `f` on every item to produce a new `History<U>`.
+	///
+	/// Committed items are mapped first, from oldest to newest, followed by undone items in the
+	/// order they would be redone. Whether each item is committed or undone, the history limit,
+	/// and the warning threshold are preserved. Any registered hooks are not.
+	///
+	/// This is useful for wrapping items in another type, or converting them to a different
+	/// representation.
+	pub fn map<U>(self, mut f: impl FnMut(T) -> U) -> History<U> {
+		let committed = self.committed.into_iter().map(&mut f).collect();
+		let mut undone: Vec<U> = self.undone.into_iter().rev().map(f).collect();
+		undone.reverse();
+
+		History {
+			committed,
+			undone,
+			limit: self.limit,
+			warn_threshold: self.warn_threshold,
+			mode: self.mode,
+			hooks: Hooks::new(),
+			save_progress: self.save_progress,
 		}
 	}
 }
 
 impl<T> History<T> {
 	/// Clears the history of all items.
+	///
+	/// The allocated capacity is kept. See [`Self::clear_and_shrink()`] to release it as well.
 	pub fn clear(&mut self) {
+		self.mark_changed_from(0);
 		self.committed.clear();
 		self.undone.clear();
 	}
 
+	/// Clears the history of all items, as with [`Self::clear()`], but returns the cleared items
+	/// rather than dropping them. This allows any teardown to be run on each item first.
+	///
+	/// Returns the committed items and the undone items, in the same orders as
+	/// [`Self::iter_committed()`] and [`Self::iter_undone()`] respectively.
+	#[must_use = "use `History::clear()` if the cleared items are not needed"]
+	pub fn clear_returning(&mut self) -> (Vec<T>, Vec<T>) {
+		self.mark_changed_from(0);
+		let committed = self.committed.drain(..).collect();
+		let undone = self.undone.drain(..).rev().collect();
+		(committed, undone)
+	}
+
+	/// Replaces the committed items with `items`, returning the previous committed items from
+	/// oldest to newest. `items` should likewise be ordered from oldest to newest.
+	///
+	/// This is useful for restoring a previously-saved set of applied items in one call. The
+	/// undone list is cleared, as redoing items on top of a different set of committed items would
+	/// not make sense. If a history limit is set, the oldest of `items` are removed until the
+	/// limit is met.
+	///
+	/// As the caller is replacing the history on purpose, the `on_evict` hook is not called for
+	/// the cleared undone items, as with [`Self::clear()`]. It is still called for any of `items`
+	/// removed to meet the history limit.
+	pub fn replace_committed(&mut self, items: Vec<T>) -> Vec<T> {
+		self.mark_changed_from(0);
+		let previous = mem::replace(&mut self.committed, items.into());
+		self.truncate_committed_to_limit_plus(0);
+		self.undone.clear();
+
+		self.assert_invariants();
+
+		previous.into()
+	}
+
 	/// Clears the history of all undone items. This prevents [`History::redo()`] from re-applying
 	/// any such items.
 	pub fn clear_undone(&mut self) {
@@ -66,15 +200,86 @@ impl<T> History<T> {
 	/// Pushes an item to the history. This also clears the undone list.
 	///
 	/// If a history limit is set, any items past the limit will be removed, plus one more to make
-	/// space for the item being pushed.
+	/// space for the item being pushed. If [`Self::mode`] is [`HistoryMode::Disabled`], the item is
+	/// dropped instead.
 	pub fn push(&mut self, item: T) {
+		if self.mode == HistoryMode::Disabled {
+			self.evict_undone();
+			return;
+		}
+
+		let len_before = self.committed.len();
 		self.truncate_committed_to_limit_plus(1);
+		self.hooks.call_on_push(&item);
 		self.committed.push_back(item);
-		self.clear_undone();
+		self.evict_undone();
+		self.check_warn_threshold(len_before);
+
+		self.assert_invariants();
+	}
+
+	/// Registers a callback to be called whenever an item is pushed to the history, including
+	/// through [`Extend`]. This replaces any callback previously registered through this method.
+	///
+	/// This is useful for keeping external bookkeeping (such as an index of items by some key) in
+	/// sync with the history.
+	///
+	/// The callback is only given a reference to the item being pushed - it cannot access or
+	/// modify the history itself. Callbacks are not carried over when the history is cloned, and
+	/// are ignored when comparing or hashing histories.
+	pub fn set_on_push(&mut self, hook: Hook<T>) {
+		self.hooks.on_push = Some(hook);
+	}
+
+	/// Removes the callback registered through [`Self::set_on_push()`], if any.
+	pub fn clear_on_push(&mut self) {
+		self.hooks.on_push = None;
+	}
+
+	/// Registers a callback to be called for each item the history drops on its own. This
+	/// replaces any callback previously registered through this method.
+	///
+	/// Items are evicted when they are removed to stay within the history limit, and when undone
+	/// items are discarded because a new item was pushed (including through [`Extend`] and
+	/// [`Self::merge_by_key()`]). Items removed explicitly, such as through [`Self::clear()`], are
+	/// not considered evicted.
+	///
+	/// As with [`Self::set_on_push()`], callbacks are not carried over when the history is cloned,
+	/// and are ignored when comparing or hashing histories.
+	pub fn set_on_evict(&mut self, hook: Hook<T>) {
+		self.hooks.on_evict = Some(hook);
+	}
+
+	/// Removes the callback registered through [`Self::set_on_evict()`], if any.
+	pub fn clear_on_evict(&mut self) {
+		self.hooks.on_evict = None;
+	}
+
+	/// Registers a callback to be called when the number of committed items reaches
+	/// [`Self::warn_threshold`]. This replaces any callback previously registered through this
+	/// method.
+	///
+	/// The callback is given the number of committed items. It is only called when that number
+	/// goes from below the threshold to at or above it - not for every push while the history
+	/// stays above the threshold. Once the history drops back below the threshold (such as
+	/// through [`Self::undo()`] or [`Self::clear()`]), the callback can be called again.
+	///
+	/// As with [`Self::set_on_push()`], callbacks are not carried over when the history is cloned,
+	/// and are ignored when comparing or hashing histories.
+	pub fn set_on_warn_threshold(&mut self, hook: ThresholdHook) {
+		self.hooks.on_warn_threshold = Some(hook);
+	}
+
+	/// Removes the callback registered through [`Self::set_on_warn_threshold()`], if any.
+	pub fn clear_on_warn_threshold(&mut self) {
+		self.hooks.on_warn_threshold = None;
 	}
 
 	/// Marks the last undone item as "committed", and returns a mutable reference to it.
 	///
+	/// If a history limit is set, and there are already as many committed items as the limit
+	/// allows, the oldest committed items are removed to make space.
+	///
 	/// # Errors
 	/// * [`Error::NoApplicableHistory`] - If there is no history available to redo. This usually
 	///   occurs if there haven't been any calls to [`Self::undo()`] since the last time an item was
@@ -91,8 +296,12 @@ impl<T> History<T> {
 			return Err(Error::NoApplicableHistory);
 		};
 
-		// And add that item to the end of the committed list.
+		// And add that item to the end of the committed list, making space for it if needed. This
+		// only ever removes committed items, so the remaining undone items can still be redone.
+		let len_before = self.committed.len();
+		self.truncate_committed_to_limit_plus(1);
 		self.committed.push_back(last_undone_item);
+		self.check_warn_threshold(len_before);
 
 		// Finally, return a mutable reference to the item we just moved between lists.
 		//
@@ -108,6 +317,81 @@ impl<T> History<T> {
 		Ok(item_ref)
 	}
 
+	/// Calls `f` on the last undone item, then redoes it as [`Self::redo()`] does if `f` succeeded.
+	///
+	/// If `f` fails, the item stays undone, and no committed items are removed to make space for
+	/// it.
+	///
+	/// # Errors
+	/// * [`Error::NoApplicableHistory`] - If there is no history available to redo.
+	/// * Any error returned by `f`.
+	pub(crate) fn try_redo(
+		&mut self,
+		f: impl FnOnce(&mut T) -> Result<(), Error>,
+	) -> Result<(), Error> {
+		let item = self.undone.last_mut().ok_or(Error::NoApplicableHistory)?;
+		f(item)?;
+		self.redo().map(|_| ())
+	}
+
+	/// Moves all committed items from `other` into `self`, interleaving them in ascending order of
+	/// the key returned by `f`.
+	///
+	/// Both histories' committed items are expected to already be in ascending order of that key.
+	/// The merge is stable - items with equal keys keep `self`'s items before `other`'s. Afterwards,
+	/// the undone list is cleared (as with [`Self::push()`]), and `other`'s undone items are
+	/// dropped. If a history limit is set, the oldest items past the limit are removed.
+	pub fn merge_by_key<K: Ord>(&mut self, other: Self, mut f: impl FnMut(&T) -> K) {
+		let len_before = self.committed.len();
+		self.mark_changed_from(0);
+		let mut ours = core::mem::take(&mut self.committed).into_iter().peekable();
+		let mut theirs = other.committed.into_iter().peekable();
+
+		// Repeatedly take whichever front item has the lower key, until one side runs out.
+		while let (Some(our_item), Some(their_item)) = (ours.peek(), theirs.peek()) {
+			let next_item = if f(their_item) < f(our_item) {
+				theirs.next()
+			} else {
+				ours.next()
+			};
+			self.committed.extend(next_item);
+		}
+
+		// At most one of these still has items, which all come after what we've merged so far.
+		self.committed.extend(ours);
+		self.committed.extend(theirs);
+
+		self.evict_undone();
+		self.truncate_committed_to_limit_plus(0);
+		self.check_warn_threshold(len_before);
+
+		self.assert_invariants();
+	}
+
+	/// Prepends items to the committed list, such that they are older than every item already in
+	/// the history. Items should be given from oldest to newest, so the first item becomes the
+	/// new oldest committed item.
+	///
+	/// This is useful for reconstructing a history from chunks which are loaded out of order.
+	/// Unlike pushing, this leaves the undone list untouched. If a history limit is set, the oldest
+	/// items are removed until the limit is met, which may include some or all of the prepended
+	/// items.
+	pub fn extend_front<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+		let len_before = self.committed.len();
+		let items: Vec<T> = iter
+			.into_iter()
+			.inspect(|item| self.hooks.call_on_push(item))
+			.collect();
+		self.mark_changed_from(0);
+		for item in items.into_iter().rev() {
+			self.committed.push_front(item);
+		}
+		self.truncate_committed_to_limit_plus(0);
+		self.check_warn_threshold(len_before);
+
+		self.assert_invariants();
+	}
+
 	/// Marks the last committed item as "undone", and returns a mutable reference to it.
 	///
 	/// # Errors
@@ -123,6 +407,7 @@ impl<T> History<T> {
 		let Some(last_committed_item) = self.committed.pop_back() else {
 			return Err(Error::NoApplicableHistory);
 		};
+		self.mark_changed_from(self.committed.len());
 
 		// And add that item to the end of the undone list.
 		self.undone.push(last_committed_item);
@@ -140,6 +425,326 @@ impl<T> History<T> {
 
 		Ok(item_ref)
 	}
+
+	/// Undoes the last committed item if there is one, and otherwise redoes the last undone item.
+	///
+	/// Undoing takes precedence, so this only redoes once every item has been undone. When the
+	/// history holds a single item, calling this repeatedly flips that item between committed and
+	/// undone, which is useful for comparing the state before and after an action.
+	///
+	/// # Errors
+	/// * [`Error::NoApplicableHistory`] - If the history is empty.
+	pub fn toggle_last(&mut self) -> Result<(), Error> {
+		if self.committed.is_empty() {
+			self.redo().map(|_| ())
+		} else {
+			self.undo().map(|_| ())
+		}
+	}
+
+	/// Sets the history limit, immediately removing the oldest committed items until the limit is
+	/// met.
+	///
+	/// Only committed items count towards the limit, so undone items are left untouched, and can
+	/// still be redone. Redoing them removes further committed items as needed, as with
+	/// [`Self::redo()`].
+	///
+	/// Setting [`Self::limit`] directly instead only removes items the next time one is pushed.
+	pub fn set_limit(&mut self, limit: Option<NonZeroUsize>) {
+		self.limit = limit;
+		self.truncate_committed_to_limit_plus(0);
+
+		self.assert_invariants();
+	}
+
+	/// Removes the most recently committed item from the history, and returns it.
+	///
+	/// Unlike [`Self::undo()`], the item is not moved to the undone list.
+	pub(crate) fn pop_committed(&mut self) -> Option<T> {
+		let item = self.committed.pop_back()?;
+		self.mark_changed_from(self.committed.len());
+		Some(item)
+	}
+
+	/// Puts an item previously taken by [`Self::pop_committed()`] back onto the committed list.
+	/// This also clears the undone list.
+	///
+	/// Unlike [`Self::push()`], this does not call the `on_push` hook, check the warning threshold,
+	/// or enforce the history limit, as the item is taking the place of one that was already there.
+	pub(crate) fn restore_committed(&mut self, item: T) {
+		self.committed.push_back(item);
+		self.evict_undone();
+	}
+
+	/// Removes the committed items within `range`, returning them as an iterator. Committed items
+	/// after `range` are shifted down to fill the gap.
+	///
+	/// Indices start at 0 for the oldest committed item. Undone items are left untouched.
+	///
+	/// Note that items in a history often depend on the items before them (for example, an item
+	/// which modifies an entity spawned by an earlier item). Removing items from the middle of the
+	/// history can leave later items in a state where they can no longer be correctly undone or
+	/// redone.
+	///
+	/// If the returned iterator is dropped before being fully consumed, the remaining items in
+	/// `range` are still removed.
+	///
+	/// # Panics
+	/// Panics if `range` is out of bounds of the committed list, or if its start is greater than
+	/// its end.
+	pub fn drain_committed_range(&mut self, range: Range<usize>) -> impl Iterator<Item = T> + '_ {
+		self.mark_changed_from(range.start);
+		self.committed.drain(range)
+	}
+
+	/// Rotates the committed items `mid` places to the left, such that the item at index `mid`
+	/// becomes the oldest committed item, and the items before it are moved to the newest end.
+	///
+	/// Indices start at 0 for the oldest committed item. Undone items are left untouched.
+	///
+	/// This is only safe for items whose effects do not depend on the order they're applied in.
+	/// As with [`Self::drain_committed_range()`], reordering items which depend on each other can
+	/// leave them in a state where they can no longer be correctly undone or redone.
+	///
+	/// # Panics
+	/// Panics if `mid` is greater than the number of committed items.
+	pub fn rotate_committed(&mut self, mid: usize) {
+		self.mark_changed_from(0);
+		self.committed.rotate_left(mid);
+	}
+
+	/// Undoes up to `count` items, one at a time, calling `f` on each item as it's undone. Returns
+	/// the number of items that were undone.
+	///
+	/// Items are undone from most-recently committed to least-recently committed. This stops early
+	/// if there are no more committed items to undo.
+	pub fn undo_n_each(&mut self, count: usize, mut f: impl FnMut(&mut T)) -> usize {
+		let mut undone_count = 0;
+
+		while undone_count < count {
+			let Ok(item) = self.undo() else {
+				break;
+			};
+			f(item);
+			undone_count += 1;
+		}
+
+		undone_count
+	}
+}
+
+/// Accessors.
+impl<T> History<T> {
+	/// Returns the position of the history's cursor within [`Self::iter()`].
+	///
+	/// Items before this index are committed, and items at or after it are undone. This is equal
+	/// to the number of committed items, and is useful for rendering a timeline of the history
+	/// with the cursor in the right place.
+	#[must_use]
+	pub fn split_index(&self) -> usize {
+		self.committed.len()
+	}
+
+	/// Returns `true` if the history's cursor is at the start of the history - that is, there are
+	/// no committed items, such as when every item has been undone.
+	#[must_use]
+	pub fn is_at_start(&self) -> bool {
+		self.committed.is_empty()
+	}
+
+	/// Returns `true` if the history's cursor is at the end of the history - that is, there are no
+	/// undone items, and so nothing to redo.
+	#[must_use]
+	pub const fn is_at_end(&self) -> bool {
+		self.undone.is_empty()
+	}
+
+	/// Searches the committed items from newest to oldest, returning the index of the first one
+	/// matching `pred`, if any.
+	///
+	/// The index counts from the oldest committed item, as with [`Self::iter_committed()`]. The
+	/// number of undos needed to undo the matching item is therefore
+	/// `self.split_index() - index`, which is useful for (say) undoing back to the last operation
+	/// affecting a given entity.
+	#[must_use]
+	pub fn rposition_committed<P: Fn(&T) -> bool>(&self, pred: P) -> Option<usize> {
+		self.committed.iter().rposition(pred)
+	}
+
+	/// Returns the item at `offset` steps away from the history's cursor, if any.
+	///
+	/// The cursor sits between the committed items and the undone items. Offsets are interpreted
+	/// as follows:
+	///
+	/// * Negative offsets refer to committed items, counting back from the newest. `-1` is the item
+	///   that [`Self::undo()`] would affect, `-2` is the one before it, and so on.
+	/// * Positive offsets refer to undone items, counting forward from the cursor. `1` is the item
+	///   that [`Self::redo()`] would affect, `2` is the one after it, and so on.
+	/// * An offset of `0` refers to the cursor itself, and so always returns `None`.
+	///
+	/// Returns `None` if no item exists at that offset.
+	#[must_use]
+	pub fn peek(&self, offset: isize) -> Option<&T> {
+		let steps = offset.unsigned_abs();
+		if steps == 0 {
+			return None;
+		}
+
+		if offset < 0 {
+			let index = self.committed.len().checked_sub(steps)?;
+			self.committed.get(index)
+		} else {
+			let index = self.undone.len().checked_sub(steps)?;
+			self.undone.get(index)
+		}
+	}
+
+	/// Returns a mutable reference to the item at `offset` steps away from the history's cursor,
+	/// if any.
+	///
+	/// Offsets are interpreted the same way as in [`Self::peek()`].
+	#[must_use]
+	pub fn peek_mut(&mut self, offset: isize) -> Option<&mut T> {
+		let steps = offset.unsigned_abs();
+		if steps == 0 {
+			return None;
+		}
+
+		if offset < 0 {
+			let index = self.committed.len().checked_sub(steps)?;
+			self.committed.get_mut(index)
+		} else {
+			let index = self.undone.len().checked_sub(steps)?;
+			self.undone.get_mut(index)
+		}
+	}
+
+	/// Returns the undone items as a slice, allowing random access to them.
+	///
+	/// Items are ordered from oldest-undone to most-recently-undone - that is, index 0 is the item
+	/// that would be redone last, and the final item is the one [`Self::redo()`] would affect.
+	/// This is the reverse of the order used by [`Self::iter_undone()`].
+	///
+	/// The committed items are stored in a [`VecDeque`], and so can't be offered as a single slice.
+	#[must_use]
+	pub fn undone_as_slice(&self) -> &[T] {
+		&self.undone
+	}
+
+	/// Returns a mutable reference to the oldest committed item, if any.
+	#[must_use]
+	pub fn first_committed_mut(&mut self) -> Option<&mut T> {
+		self.committed.front_mut()
+	}
+
+	/// Returns a mutable reference to the newest committed item - the one [`Self::undo()`] would
+	/// affect - if any.
+	///
+	/// This is useful for patching the most recent item, such as to fix a stale `Entity` it holds,
+	/// or to fill in data that was only learned after the item was pushed.
+	#[must_use]
+	pub fn last_committed_mut(&mut self) -> Option<&mut T> {
+		self.committed.back_mut()
+	}
+
+	/// Returns a mutable reference to the first undone item - the one [`Self::redo()`] would
+	/// affect - if any.
+	///
+	/// As with [`Self::iter_undone()`], undone items are ordered from most-recently undone to
+	/// least-recently undone.
+	#[must_use]
+	pub fn first_undone_mut(&mut self) -> Option<&mut T> {
+		self.undone.last_mut()
+	}
+
+	/// Returns a mutable reference to the last undone item - the one that would be redone after
+	/// every other undone item - if any.
+	#[must_use]
+	pub fn last_undone_mut(&mut self) -> Option<&mut T> {
+		self.undone.first_mut()
+	}
+}
+
+/// Capacity.
+impl<T> History<T> {
+	/// Returns the number of committed items the history can hold without reallocating.
+	#[must_use]
+	pub fn committed_capacity(&self) -> usize {
+		self.committed.capacity()
+	}
+
+	/// Returns the number of undone items the history can hold without reallocating.
+	#[must_use]
+	pub const fn undone_capacity(&self) -> usize {
+		self.undone.capacity()
+	}
+
+	/// Shrinks the capacity of both the committed and undone lists as much as possible.
+	pub fn shrink_to_fit(&mut self) {
+		self.committed.shrink_to_fit();
+		self.undone.shrink_to_fit();
+	}
+
+	/// Clears the history of all items, keeping the allocated capacity of both the committed and
+	/// undone lists. This is the same as [`Self::clear()`], and is useful for reusing the
+	/// history's allocations, such as when switching between documents.
+	pub fn clear_keep_capacity(&mut self) {
+		self.clear();
+	}
+
+	/// Clears the history of all items, and releases as much of the allocated capacity of both the
+	/// committed and undone lists as possible.
+	pub fn clear_and_shrink(&mut self) {
+		self.clear();
+		self.shrink_to_fit();
+	}
+
+	/// Returns an estimate of the number of bytes used by this history's committed and undone
+	/// lists.
+	///
+	/// This is based on the lists' capacities, and so includes space that has been allocated but
+	/// not yet used. It does not include any memory owned by the items themselves - see
+	/// [`Self::deep_memory_usage()`] for that.
+	#[must_use]
+	pub fn memory_usage(&self) -> usize {
+		(self.committed.capacity() + self.undone.capacity()) * size_of::<T>()
+	}
+
+	/// Reserves capacity for at least `additional` more undone items, beyond those currently
+	/// undone. This is useful when you're about to undo a large number of items at once.
+	///
+	/// # Panics
+	/// Panics if the new capacity overflows `isize::MAX` bytes.
+	pub fn reserve_undone(&mut self, additional: usize) {
+		self.undone.reserve(additional);
+	}
+}
+
+/// Capacity, for items which can report their own size.
+impl<T: HeapSize> History<T> {
+	/// Returns an estimate of the number of bytes used by this history, including any heap memory
+	/// owned by its items.
+	///
+	/// This is the same as [`Self::memory_usage()`], plus the [`HeapSize::heap_size()`] of every
+	/// committed and undone item.
+	#[must_use]
+	pub fn deep_memory_usage(&self) -> usize {
+		let items_heap_size: usize = self.iter().map(HeapSize::heap_size).sum();
+		self.memory_usage() + items_heap_size
+	}
+}
+
+/// Undo/redo, for items which can be cloned.
+impl<T: Clone> History<T> {
+	/// Marks the last committed item as "undone", as with [`Self::undo()`], and returns a clone of
+	/// it, or `None` if there is no history available to undo.
+	///
+	/// The item itself stays in the undone list, so it can still be redone later. This is useful
+	/// when the undo is carried out elsewhere - for example, a client which sends the undone
+	/// operation to a server to be applied there, rather than applying it locally.
+	pub fn undo_detach(&mut self) -> Option<T> {
+		self.undo().ok().map(|item| item.clone())
+	}
 }
 
 /// Iterator items.
@@ -161,11 +766,167 @@ impl<T> History<T> {
 	pub fn iter_undone(&self) -> UndoneIter<'_, T> {
 		UndoneIter::new(self.undone.iter())
 	}
+
+	/// Returns an iterator over this history, as with [`Self::iter()`], with each item paired with
+	/// its position in the timeline.
+	///
+	/// This is useful for displaying the history as a list, with a marker at the history's current
+	/// position.
+	pub fn iter_timeline(&self) -> impl Iterator<Item = TimelineEntry<&T>> + '_ {
+		let split_index = self.split_index();
+		self.iter()
+			.enumerate()
+			.map(move |(index, item)| TimelineEntry {
+				item,
+				index,
+				committed: index < split_index,
+				is_current: index + 1 == split_index,
+			})
+	}
+
+	/// Returns an iterator over committed items, starting at the cursor and walking backwards -
+	/// that is, in the order that repeated calls to [`Self::undo()`] would affect them.
+	pub fn iter_undo_order(&self) -> Rev<CommittedIter<'_, T>> {
+		self.iter_committed().rev()
+	}
+
+	/// Returns an iterator over undone items, starting at the cursor and walking forwards - that
+	/// is, in the order that repeated calls to [`Self::redo()`] would affect them.
+	///
+	/// This is the same order as [`Self::iter_undone()`].
+	#[must_use]
+	pub fn iter_redo_order(&self) -> UndoneIter<'_, T> {
+		self.iter_undone()
+	}
+
+	/// Returns an iterator over the committed items in pages of `size` items, from oldest to
+	/// newest. The last page holds whatever items remain, and so may be smaller than `size`.
+	///
+	/// This is useful for sending a history in pieces, such as to a paginated UI or over a network.
+	///
+	/// # Panics
+	/// Panics if `size` is zero.
+	pub fn committed_chunks(&self, size: usize) -> impl Iterator<Item = Vec<&T>> + '_ {
+		assert!(size != 0, "chunk size must be non-zero");
+		let len = self.committed.len();
+		(0..len)
+			.step_by(size)
+			.map(move |start| self.committed.range(start..len.min(start + size)).collect())
+	}
+}
+
+/// Persistence.
+impl<T> History<T> {
+	/// Returns the committed items added since `marker`, along with a new marker to pass to the
+	/// next call. Passing `0` returns every committed item.
+	///
+	/// This allows a persistence layer to append only new items to disk, rather than saving the
+	/// whole history each time. Markers count every item ever committed, including those since
+	/// trimmed by the history limit, so they stay valid as old items are trimmed. The returned
+	/// items start at position `new_marker - items.len()`:
+	/// * If this is before `marker`, the items saved from that position onwards have since been
+	///   undone, removed, or rearranged (such as through [`Self::undo()`] or [`Self::clear()`]),
+	///   and should be replaced by the returned items.
+	/// * If this is after `marker`, the items in between were trimmed before they could be
+	///   returned.
+	///
+	/// Changes are tracked from one call to the next, so each history should only have one caller
+	/// of this method. Changes made to items in place (such as through [`Self::peek_mut()`]) are
+	/// not tracked.
+	pub fn take_new_since(&mut self, marker: usize) -> (&[T], usize) {
+		let len = self.committed.len();
+		let trimmed = self.save_progress.trimmed;
+		let from = self
+			.save_progress
+			.changed_from
+			.take()
+			.map_or(marker, |changed_from| changed_from.min(marker));
+		let start = from
+			.checked_sub(trimmed)
+			.filter(|&start| start <= len)
+			.unwrap_or(0);
+		(&self.committed.make_contiguous()[start..], trimmed + len)
+	}
+}
+
+/// Debugging.
+impl<T> History<T> {
+	/// Checks that this history is internally consistent, panicking if not. The checks are only
+	/// made in debug builds.
+	///
+	/// This is called after each method which enforces the history limit, and is useful in tests
+	/// and when developing new ways to modify a history.
+	///
+	/// # Panics
+	/// In debug builds, panics if:
+	/// * There are more committed items than the history limit allows - for example, because the
+	///   limit was lowered directly, without any items being pushed since.
+	/// * The number of trimmed items has gone down since the last check.
+	/// * The oldest changed position recorded for [`Self::take_new_since()`] lies after the split
+	///   index.
+	pub(crate) fn assert_invariants(&mut self) {
+		if let Some(limit) = self.limit {
+			debug_assert!(
+				self.committed.len() <= limit.get(),
+				"history has {} committed items, more than its limit of {limit}",
+				self.committed.len(),
+			);
+		}
+
+		let progress = &mut self.save_progress;
+		debug_assert!(
+			progress.trimmed >= progress.checked_trimmed,
+			"history has trimmed {} items, fewer than the {} it had trimmed before",
+			progress.trimmed,
+			progress.checked_trimmed,
+		);
+		progress.checked_trimmed = progress.trimmed;
+
+		if let Some(changed_from) = progress.changed_from {
+			debug_assert!(
+				changed_from <= progress.trimmed + self.committed.len(),
+				"history has changes recorded from position {changed_from}, past its split index",
+			);
+		}
+	}
+}
+
+/// Comparisons.
+impl<T: PartialEq> History<T> {
+	/// Compares this history's committed items against those of `other`, an older snapshot of this
+	/// history.
+	///
+	/// The returned [`Diff`] describes which committed items were added since `other` was
+	/// taken, and which of `other`'s committed items are no longer present (for example, because
+	/// they were undone and then replaced). Undone items are not compared.
+	#[must_use]
+	pub fn diff<'a>(&'a self, other: &'a Self) -> Diff<'a, T> {
+		let common_len = self
+			.committed
+			.iter()
+			.zip(&other.committed)
+			.take_while(|(ours, theirs)| ours == theirs)
+			.count();
+
+		Diff {
+			common_len,
+			added: self.committed.iter().skip(common_len).collect(),
+			removed: other.committed.iter().skip(common_len).collect(),
+		}
+	}
 }
 
 /// Private items. This helps keep the secondary side bar in vscode cleaner, by separating this
 /// module into public and private items.
 impl<T> History<T> {
+	/// Records that the committed item at `index` (and so every item after it) has been removed,
+	/// inserted, or moved, so that [`Self::take_new_since()`] returns those items again.
+	fn mark_changed_from(&mut self, index: usize) {
+		let position = self.save_progress.trimmed + index;
+		let changed_from = self.save_progress.changed_from.get_or_insert(position);
+		*changed_from = (*changed_from).min(position);
+	}
+
 	/// Truncates `self.committed` such that it only contains `self.limit` items.
 	///
 	/// This also takes a parameter `plus`, which causes the truncation to act as if
@@ -182,13 +943,33 @@ impl<T> History<T> {
 			// Then, calculate how many items to remove, saturating at 0.
 			let count_to_remove = len_after_push.saturating_sub(limit);
 
-			// Then, drain that many items out of the beginning of the committed list.
-			//
-			// Technically speaking, draining creates an iterator that moves items off the list,
-			// rather than directly removing items. However, dropping that iterator gives those
-			// drained items nowhere to go - and so they too will be dropped. In essence, this is
-			// like a theoretical `truncate_front()` function on `VecDeque`.
-			self.committed.drain(0..count_to_remove);
+			// Then, drain that many items out of the beginning of the committed list, letting the
+			// `on_evict` hook see each one before it's dropped.
+			for item in self.committed.drain(0..count_to_remove) {
+				self.hooks.call_on_evict(&item);
+			}
+			self.save_progress.trimmed += count_to_remove;
+		}
+	}
+
+	/// Calls the `on_warn_threshold` hook if the number of committed items has gone from below
+	/// [`Self::warn_threshold`] (when it was `len_before`) to at or above it.
+	fn check_warn_threshold(&mut self, len_before: usize) {
+		let Some(threshold) = self.warn_threshold else {
+			return;
+		};
+
+		let len = self.committed.len();
+		if len_before < threshold.get() && len >= threshold.get() {
+			self.hooks.call_on_warn_threshold(len);
+		}
+	}
+
+	/// Clears the undone list, as with [`Self::clear_undone()`], but calls the `on_evict` hook for
+	/// each item first.
+	pub(crate) fn evict_undone(&mut self) {
+		for item in self.undone.drain(..).rev() {
+			self.hooks.call_on_evict(&item);
 		}
 	}
 }
@@ -203,25 +984,87 @@ impl<T> Default for History<T> {
 impl<T> Extend<T> for History<T> {
 	fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
 		match self.limit {
-			None => {
+			None if self.mode == HistoryMode::Recording => {
 				// In this case, we can just defer to VecDeque's Extend impl.
+				let len_before = self.committed.len();
+				let iter = iter
+					.into_iter()
+					.inspect(|item| self.hooks.call_on_push(item));
 				self.committed.extend(iter);
-				self.clear_undone();
+				self.evict_undone();
+				self.check_warn_threshold(len_before);
 			}
-			Some(_limit) => {
-				// In this case, we need to account for the limit - and ideally, do so without ever
-				// having more than `limit` items in `self.committed`, even for a moment.
+			_ => {
+				// In this case, we need to account for the limit (or the mode) - and ideally, do so
+				// without ever having more than `limit` items in `self.committed`, even for a
+				// moment.
 				//
 				// TODO: Depending on how Rust optimizes this code, this could be made more
 				// efficient.
 				for item in iter {
 					self.push(item);
 				}
+				// Pushing clears the undone list, but only if anything was pushed. Clear it here
+				// too, so that extending with nothing behaves the same regardless of the limit.
+				self.evict_undone();
 			}
 		}
 	}
 }
 
+/// Bookkeeping for [`History::take_new_since()`], tracking how the committed items have changed.
+///
+/// Positions count every item ever committed to the history, including those since trimmed.
+///
+/// This is not part of a history's contents. As such, it is ignored when comparing or hashing
+/// histories.
+#[derive(Clone, Copy, Debug, Default)]
+struct SaveProgress {
+	/// The number of committed items trimmed from the front of the history so far. This only ever
+	/// grows.
+	trimmed: usize,
+	/// The position of the oldest committed item which has been removed, inserted, or moved (other
+	/// than by trimming) since [`History::take_new_since()`] was last called, if any.
+	changed_from: Option<usize>,
+	/// The value of `trimmed` when [`History::assert_invariants()`] last ran.
+	checked_trimmed: usize,
+}
+
+impl SaveProgress {
+	/// Creates a new `SaveProgress`, for a history which has never changed.
+	const fn new() -> Self {
+		Self {
+			trimmed: 0,
+			changed_from: None,
+			checked_trimmed: 0,
+		}
+	}
+}
+
+impl Hash for SaveProgress {
+	fn hash<H: Hasher>(&self, _state: &mut H) {}
+}
+
+impl PartialEq for SaveProgress {
+	fn eq(&self, _other: &Self) -> bool {
+		true
+	}
+}
+
+impl Eq for SaveProgress {}
+
+impl PartialOrd for SaveProgress {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl Ord for SaveProgress {
+	fn cmp(&self, _other: &Self) -> Ordering {
+		Ordering::Equal
+	}
+}
+
 impl<T> FromIterator<T> for History<T> {
 	fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
 		Self {
@@ -230,3 +1073,625 @@ impl<T> FromIterator<T> for History<T> {
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use core::time::Duration;
+	use rstest::rstest;
+	use std::{
+		sync::{Arc, Mutex},
+		time::Instant,
+	};
+
+	#[rstest]
+	fn reserve_undone_grows_undone_capacity() {
+		let mut history = History::<u32>::new();
+		assert_eq!(history.undone_capacity(), 0);
+
+		history.reserve_undone(16);
+		assert!(history.undone_capacity() >= 16);
+		assert_eq!(history.committed_capacity(), 0);
+
+		history.extend(0..8);
+		assert!(history.committed_capacity() >= 8);
+	}
+
+	#[rstest]
+	fn clearing_keeps_or_releases_capacity() {
+		let mut history: History<u32> = (0..8).collect();
+		let _ = history.undo_n_each(4, |_| {});
+
+		history.clear_keep_capacity();
+		assert_eq!(history.iter().count(), 0);
+		assert!(history.committed_capacity() >= 4);
+		assert!(history.undone_capacity() >= 4);
+
+		history.clear_and_shrink();
+		assert_eq!(history.committed_capacity(), 0);
+		assert_eq!(history.undone_capacity(), 0);
+	}
+
+	impl HeapSize for Vec<u8> {
+		fn heap_size(&self) -> usize {
+			self.capacity()
+		}
+	}
+
+	#[rstest]
+	fn memory_usage_tracks_pushes_and_shrinking() {
+		let mut history = History::<Vec<u8>>::new();
+		assert_eq!(history.memory_usage(), 0);
+
+		history.extend((0..4).map(|_| vec![0; 100]));
+		let shallow = history.memory_usage();
+		assert!(shallow >= 4 * size_of::<Vec<u8>>());
+		assert!(history.deep_memory_usage() >= shallow + 400);
+
+		history.clear();
+		history.shrink_to_fit();
+		assert_eq!(history.memory_usage(), 0);
+		assert_eq!(history.deep_memory_usage(), 0);
+	}
+
+	#[rstest]
+	#[case::fewer_than_committed(3, vec![4, 3, 2])]
+	#[case::more_than_committed(8, vec![4, 3, 2, 1, 0])]
+	fn undo_n_each_calls_back_in_undo_order(#[case] count: usize, #[case] expected: Vec<u32>) {
+		let mut history: History<u32> = (0..5).collect();
+
+		let mut seen = vec![];
+		let undone_count = history.undo_n_each(count, |item| seen.push(*item));
+
+		assert_eq!(undone_count, expected.len());
+		assert_eq!(seen, expected);
+		assert_eq!(history.iter_undone().count(), expected.len());
+	}
+
+	#[rstest]
+	fn clear_returning_gives_back_items_in_iteration_order() {
+		let mut history: History<u32> = (0..5).collect();
+		let _ = history.undo_n_each(2, |_| {});
+
+		let (committed, undone) = history.clear_returning();
+		assert_eq!(committed, [0, 1, 2]);
+		assert_eq!(undone, [3, 4]);
+		assert_eq!(history.iter().count(), 0);
+	}
+
+	#[rstest]
+	fn replace_committed_returns_old_items_and_applies_limit() {
+		let mut history: History<u32> = (0..4).collect();
+		history.limit = NonZeroUsize::new(3);
+		let _ = history.undo_n_each(1, |_| {});
+
+		let previous = history.replace_committed(vec![10, 11, 12, 13, 14]);
+		assert_eq!(previous, [0, 1, 2]);
+		assert_eq!(
+			history.iter_committed().collect::<Vec<_>>(),
+			[&12, &13, &14]
+		);
+		assert_eq!(history.iter_undone().count(), 0);
+	}
+
+	#[rstest]
+	fn undone_as_slice_is_ordered_oldest_undone_first() {
+		let mut history: History<u32> = (0..5).collect();
+		assert!(history.undone_as_slice().is_empty());
+
+		let _ = history.undo_n_each(3, |_| {});
+		assert_eq!(history.undone_as_slice(), [4, 3, 2]);
+		assert_eq!(history.undone_as_slice().last(), history.peek(1));
+	}
+
+	#[rstest]
+	fn undo_and_redo_order_iterators_match_operations() {
+		let mut history: History<u32> = (0..5).collect();
+		let undo_order: Vec<_> = history.iter_undo_order().copied().collect();
+		let mut undone = vec![];
+		while let Ok(item) = history.undo() {
+			undone.push(*item);
+		}
+		assert_eq!(undo_order, undone);
+
+		let redo_order: Vec<_> = history.iter_redo_order().copied().collect();
+		let mut redone = vec![];
+		while let Ok(item) = history.redo() {
+			redone.push(*item);
+		}
+		assert_eq!(redo_order, redone);
+		assert_eq!(redone, [0, 1, 2, 3, 4]);
+	}
+
+	#[rstest]
+	fn assert_invariants_accepts_limited_pushes() {
+		let mut history = History::new();
+		history.limit = NonZeroUsize::new(2);
+		history.extend(0..5_u32);
+		history.assert_invariants();
+	}
+
+	#[cfg(debug_assertions)]
+	#[rstest]
+	#[should_panic(expected = "more than its limit")]
+	fn assert_invariants_catches_exceeded_limit() {
+		let mut history: History<u32> = (0..5).collect();
+		history.limit = NonZeroUsize::new(2);
+		history.assert_invariants();
+	}
+
+	#[cfg(debug_assertions)]
+	#[rstest]
+	#[should_panic(expected = "fewer than the 3 it had trimmed before")]
+	fn assert_invariants_catches_shrinking_trim_count() {
+		let mut history = History::with_ring_limit(NonZeroUsize::new(2).unwrap());
+		history.extend(0..5_u32);
+		history.assert_invariants();
+		history.save_progress.trimmed = 0;
+		history.assert_invariants();
+	}
+
+	#[rstest]
+	fn disabled_mode_stores_nothing_but_clears_undone() {
+		let mut history: History<u32> = (0..3).collect();
+		let _ = history.undo_n_each(1, |_| {});
+		history.mode = HistoryMode::Disabled;
+
+		history.push(10);
+		history.extend([11, 12]);
+		assert!(history.iter_committed().eq(&[0, 1]));
+		assert_eq!(history.iter_undone().count(), 0);
+	}
+
+	#[rstest]
+	fn redo_after_lowering_limit_keeps_undone_items() {
+		let mut history: History<u32> = (0..5).collect();
+		let _ = history.undo_n_each(3, |_| {});
+		history.set_limit(NonZeroUsize::new(1));
+		assert!(history.iter_committed().eq(&[1]));
+		assert!(history.iter_undone().eq(&[2, 3, 4]));
+
+		let mut redone = vec![];
+		while let Ok(item) = history.redo() {
+			redone.push(*item);
+			history.assert_invariants();
+		}
+		assert_eq!(redone, [2, 3, 4]);
+		assert!(history.iter_committed().eq(&[4]));
+
+		assert_eq!(history.undo().copied(), Ok(4));
+		assert_eq!(history.undo(), Err(Error::NoApplicableHistory));
+	}
+
+	#[rstest]
+	fn cursor_boundaries_follow_undo_and_redo() {
+		let mut history: History<u32> = History::new();
+		assert!(history.is_at_start() && history.is_at_end());
+
+		history.extend([1, 2]);
+		assert!(!history.is_at_start() && history.is_at_end());
+
+		history.undo().unwrap();
+		assert!(!history.is_at_start() && !history.is_at_end());
+
+		history.undo().unwrap();
+		assert!(history.is_at_start() && !history.is_at_end());
+
+		history.redo().unwrap();
+		history.redo().unwrap();
+		assert!(!history.is_at_start() && history.is_at_end());
+	}
+
+	#[rstest]
+	fn toggle_last_prefers_undo() {
+		let mut history: History<u32> = History::new();
+		assert_eq!(history.toggle_last(), Err(Error::NoApplicableHistory));
+
+		history.push(1);
+		for expected in [0, 1, 0, 1] {
+			history.toggle_last().unwrap();
+			assert_eq!(history.split_index(), expected);
+		}
+
+		history.push(2);
+		history.toggle_last().unwrap();
+		history.toggle_last().unwrap();
+		assert!(history.iter_undone().eq(&[1, 2]));
+		history.toggle_last().unwrap();
+		assert!(history.iter_committed().eq(&[1]));
+	}
+
+	#[rstest]
+	#[case::unlimited(None, &[0, 1, 2, 3, 4])]
+	#[case::trims_some_prepended(NonZeroUsize::new(4), &[1, 2, 3, 4])]
+	#[case::trims_all_prepended(NonZeroUsize::new(2), &[3, 4])]
+	fn extend_front_prepends_older_items(
+		#[case] limit: Option<NonZeroUsize>,
+		#[case] expected: &[u32],
+	) {
+		let mut history: History<u32> = (2..6).collect();
+		history.limit = limit;
+		let _ = history.undo();
+
+		history.extend_front([0, 1]);
+		assert!(history.iter_committed().eq(expected));
+		assert!(history.iter_undone().eq(&[5]));
+	}
+
+	#[rstest]
+	#[case::all_committed(0, Some(4))]
+	#[case::some_undone(2, Some(2))]
+	#[case::all_undone(5, None)]
+	fn iter_timeline_marks_the_cursor(#[case] undo_count: usize, #[case] current: Option<u32>) {
+		let mut history: History<u32> = (0..5).collect();
+		let _ = history.undo_n_each(undo_count, |_| {});
+
+		let timeline: Vec<_> = history.iter_timeline().collect();
+		assert_eq!(timeline.len(), 5);
+		for (index, entry) in timeline.iter().enumerate() {
+			assert_eq!(entry.index, index);
+			assert_eq!(entry.committed, index < 5 - undo_count);
+		}
+
+		let mut current_items = timeline.iter().filter(|entry| entry.is_current);
+		assert_eq!(current_items.next().map(|entry| *entry.item), current);
+		assert!(current_items.next().is_none());
+	}
+
+	#[rstest]
+	fn split_index_follows_the_cursor() {
+		let mut history: History<u32> = (0..5).collect();
+		assert_eq!(history.split_index(), 5);
+
+		let _ = history.undo_n_each(2, |_| {});
+		assert_eq!(history.split_index(), history.iter_committed().count());
+		assert_eq!(history.iter().nth(history.split_index()), Some(&3));
+
+		history.redo().unwrap();
+		assert_eq!(history.split_index(), 4);
+		assert_eq!(history.iter().nth(history.split_index()), Some(&4));
+	}
+
+	#[rstest]
+	fn rposition_committed_finds_the_newest_match() {
+		let mut history: History<u32> = [1, 2, 3, 2, 5].into_iter().collect();
+		let _ = history.undo();
+		assert_eq!(history.rposition_committed(|&item| item == 2), Some(3));
+		assert_eq!(history.rposition_committed(|&item| item == 5), None);
+
+		let index = history.rposition_committed(|&item| item == 3).unwrap();
+		let _ = history.undo_n_each(history.split_index() - index, |_| {});
+		assert_eq!(history.peek(1), Some(&3));
+	}
+
+	#[rstest]
+	fn ring_limit_wraps_around_in_order_without_reallocating() {
+		let mut history = History::with_ring_limit(NonZeroUsize::new(4).unwrap());
+		let capacity = history.committed_capacity();
+		assert!(capacity >= 4);
+
+		history.extend(0..10);
+		assert!(history.iter_committed().eq(&[6, 7, 8, 9]));
+
+		let _ = history.undo_n_each(2, |_| {});
+		history.push(10);
+		history.push(11);
+		history.push(12);
+		assert!(history.iter_committed().eq(&[7, 10, 11, 12]));
+		assert!(history.iter().eq(&[7, 10, 11, 12]));
+		assert_eq!(history.committed_capacity(), capacity);
+	}
+
+	#[rstest]
+	#[ignore = "benchmark; run with `cargo test --release -- --ignored ring_limit`"]
+	fn ring_limit_benchmark_against_plain_limit() {
+		const LIMIT: usize = 16_384;
+
+		/// Returns the fastest of several runs of filling `make_history()` to the limit, then
+		/// pushing past it.
+		fn time_pushes(make_history: impl Fn() -> History<usize>) -> Duration {
+			(0..5)
+				.map(|_| {
+					let mut history = make_history();
+					let start = Instant::now();
+					for item in 0..LIMIT * 4 {
+						history.push(item);
+					}
+					start.elapsed()
+				})
+				.min()
+				.unwrap()
+		}
+
+		let limit = NonZeroUsize::new(LIMIT).unwrap();
+		let ring = time_pushes(|| History::with_ring_limit(limit));
+		let plain = time_pushes(|| {
+			let mut history = History::new();
+			history.limit = Some(limit);
+			history
+		});
+		println!("with_ring_limit: {ring:?}, plain limit: {plain:?}");
+		assert!(
+			ring <= plain * 2,
+			"with_ring_limit took {ring:?}, but a plain limit took {plain:?}",
+		);
+	}
+
+	#[rstest]
+	fn take_new_since_returns_only_new_items() {
+		let mut history: History<u32> = (0..3).collect();
+		history.limit = NonZeroUsize::new(4);
+
+		let (items, marker) = history.take_new_since(0);
+		assert_eq!(items, [0, 1, 2]);
+		let (items, marker) = history.take_new_since(marker);
+		assert_eq!(items, []);
+
+		// Trimming items which were already returned keeps the marker valid.
+		history.extend([3, 4]);
+		let (items, marker) = history.take_new_since(marker);
+		assert_eq!(items, [3, 4]);
+
+		history.push(5);
+		let (items, marker) = history.take_new_since(marker);
+		assert_eq!(items, [5]);
+
+		// Trimming items which were never returned invalidates the marker.
+		history.extend([6, 7, 8, 9, 10]);
+		let (items, marker) = history.take_new_since(marker);
+		assert_eq!(items, [7, 8, 9, 10]);
+
+		history.clear();
+		history.push(11);
+		let (items, marker) = history.take_new_since(marker);
+		assert_eq!(items, [11]);
+		history.push(12);
+		assert_eq!(history.take_new_since(marker).0, [12]);
+	}
+
+	#[rstest]
+	fn take_new_since_returns_items_replacing_undone_ones() {
+		let mut history: History<u32> = (0..3).collect();
+		let (_, marker) = history.take_new_since(0);
+		assert_eq!(marker, 3);
+
+		history.undo().unwrap();
+		history.push(5);
+		let (items, new_marker) = history.take_new_since(marker);
+		assert_eq!(items, [5]);
+		// The returned item replaces the one saved at position 2.
+		assert_eq!(new_marker - items.len(), 2);
+
+		let (items, _) = history.take_new_since(new_marker);
+		assert_eq!(items, []);
+	}
+
+	#[rstest]
+	fn take_new_since_returns_items_replacing_popped_ones() {
+		let mut history: History<u32> = (0..4).collect();
+		let (_, marker) = history.take_new_since(0);
+
+		let popped = history.pop_committed().unwrap();
+		let _ = history.pop_committed();
+		history.restore_committed(popped + 10);
+		let (items, new_marker) = history.take_new_since(marker);
+		assert_eq!(items, [13]);
+		assert_eq!(new_marker, 3);
+
+		history.drain_committed_range(0..1).for_each(drop);
+		let (items, new_marker) = history.take_new_since(new_marker);
+		assert_eq!(items, [1, 13]);
+		assert_eq!(new_marker - items.len(), 0);
+	}
+
+	#[rstest]
+	fn undo_detach_returns_a_clone_and_keeps_the_item() {
+		let mut history: History<u32> = (0..3).collect();
+
+		assert_eq!(history.undo_detach(), Some(2));
+		assert_eq!(history.undo_detach(), Some(1));
+		assert!(history.iter_undone().eq(&[1, 2]));
+
+		assert_eq!(history.redo(), Ok(&mut 1));
+		assert_eq!(history.undo_detach(), Some(1));
+
+		history.undo().unwrap();
+		assert_eq!(history.undo_detach(), None);
+		assert_eq!(history.iter_undone().len(), 3);
+	}
+
+	#[rstest]
+	#[case::even(5, &[5, 5])]
+	#[case::uneven(4, &[4, 4, 2])]
+	#[case::single_page(10, &[10])]
+	#[case::larger_than_history(16, &[10])]
+	fn committed_chunks_clamp_the_last_page(#[case] size: usize, #[case] expected: &[usize]) {
+		let mut history: History<u32> = (0..12).collect();
+		let _ = history.undo_n_each(2, |_| {});
+
+		let chunks: Vec<_> = history.committed_chunks(size).collect();
+		let sizes: Vec<_> = chunks.iter().map(Vec::len).collect();
+		assert_eq!(sizes, expected);
+		assert!(chunks.concat().into_iter().eq(history.iter_committed()));
+	}
+
+	#[rstest]
+	fn diff_against_older_snapshot() {
+		let mut history: History<u32> = (0..5).collect();
+		let snapshot = history.clone();
+		assert!(history.diff(&snapshot).is_empty());
+
+		// Replace the last two items, and add a new one.
+		let _ = history.undo_n_each(2, |_| {});
+		history.extend([10, 11, 12]);
+
+		let diff = history.diff(&snapshot);
+		assert_eq!(diff.common_len, 3);
+		assert_eq!(diff.added, [&10, &11, &12]);
+		assert_eq!(diff.removed, [&3, &4]);
+	}
+
+	#[rstest]
+	#[case::last_committed(-1, Some(&2))]
+	#[case::oldest_committed(-3, Some(&0))]
+	#[case::before_oldest_committed(-4, None)]
+	#[case::cursor(0, None)]
+	#[case::next_redo(1, Some(&3))]
+	#[case::last_redo(2, Some(&4))]
+	#[case::after_last_redo(3, None)]
+	fn peek(#[case] offset: isize, #[case] expected: Option<&u32>) {
+		let mut history: History<u32> = (0..5).collect();
+		let _ = history.undo_n_each(2, |_| {});
+
+		assert_eq!(history.peek(offset), expected);
+	}
+
+	#[rstest]
+	fn first_and_last_mut_patch_items_in_place() {
+		let mut history: History<u32> = (0..6).collect();
+		let _ = history.undo();
+		let _ = history.undo();
+
+		*history.first_committed_mut().unwrap() += 10;
+		*history.last_committed_mut().unwrap() += 20;
+		*history.first_undone_mut().unwrap() += 30;
+		*history.last_undone_mut().unwrap() += 40;
+
+		assert!(history.iter_committed().eq(&[10, 1, 2, 23]));
+		assert!(history.iter_undone().eq(&[34, 45]));
+
+		let mut empty = History::<u32>::new();
+		assert_eq!(empty.last_committed_mut(), None);
+		assert_eq!(empty.first_undone_mut(), None);
+	}
+
+	#[rstest]
+	fn map_preserves_order_split_and_limit() {
+		let mut history: History<u32> = (0..5).collect();
+		history.limit = NonZeroUsize::new(10);
+		let _ = history.undo_n_each(2, |_| {});
+
+		let mut call_order = vec![];
+		let mapped = history.map(|item| {
+			call_order.push(item);
+			item.to_string()
+		});
+
+		assert_eq!(call_order, [0, 1, 2, 3, 4]);
+		assert!(mapped.iter_committed().eq(["0", "1", "2"]));
+		assert!(mapped.iter_undone().eq(["3", "4"]));
+		assert_eq!(mapped.limit, NonZeroUsize::new(10));
+	}
+
+	#[rstest]
+	fn drain_committed_range_preserves_surrounding_order() {
+		let mut history: History<u32> = (0..6).collect();
+		let _ = history.undo();
+
+		let drained: Vec<u32> = history.drain_committed_range(1..3).collect();
+		assert_eq!(drained, [1, 2]);
+		assert!(history.iter_committed().eq(&[0, 3, 4]));
+		assert!(history.iter_undone().eq(&[5]));
+	}
+
+	#[rstest]
+	#[case::none(0, vec![0, 1, 2, 3])]
+	#[case::one(1, vec![1, 2, 3, 0])]
+	#[case::all(4, vec![0, 1, 2, 3])]
+	fn rotate_committed_leaves_undone_untouched(#[case] mid: usize, #[case] expected: Vec<u32>) {
+		let mut history: History<u32> = (0..6).collect();
+		let _ = history.undo();
+		let _ = history.undo();
+
+		history.rotate_committed(mid);
+		assert!(history.iter_committed().eq(&expected));
+		assert!(history.iter_undone().eq(&[4, 5]));
+	}
+
+	#[rstest]
+	#[case::unlimited(None)]
+	#[case::limited(NonZeroUsize::new(2))]
+	fn on_push_fires_for_push_and_extend(#[case] limit: Option<NonZeroUsize>) {
+		let pushed = Arc::new(Mutex::new(vec![]));
+		let mut history = History::new();
+		history.limit = limit;
+
+		let pushed_by_hook = Arc::clone(&pushed);
+		history.set_on_push(Box::new(move |item: &u32| {
+			pushed_by_hook.lock().unwrap().push(*item);
+		}));
+
+		history.push(0);
+		history.extend([1, 2]);
+		assert_eq!(*pushed.lock().unwrap(), [0, 1, 2]);
+
+		history.clear_on_push();
+		history.push(3);
+		assert_eq!(*pushed.lock().unwrap(), [0, 1, 2]);
+	}
+
+	#[rstest]
+	fn on_warn_threshold_fires_only_when_crossing_upward() {
+		let crossings = Arc::new(Mutex::new(vec![]));
+		let mut history = History::new();
+		history.warn_threshold = NonZeroUsize::new(3);
+
+		let crossings_by_hook = Arc::clone(&crossings);
+		history.set_on_warn_threshold(Box::new(move |len| {
+			crossings_by_hook.lock().unwrap().push(len);
+		}));
+
+		history.extend([0, 1]);
+		assert_eq!(*crossings.lock().unwrap(), []);
+
+		history.push(2);
+		history.push(3);
+		history.extend([4, 5]);
+		assert_eq!(*crossings.lock().unwrap(), [3]);
+
+		for _ in 0..4 {
+			history.undo().unwrap();
+		}
+		history.extend([6, 7, 8]);
+		assert_eq!(*crossings.lock().unwrap(), [3, 5]);
+
+		for _ in 0..3 {
+			history.undo().unwrap();
+		}
+		history.redo().unwrap();
+		history.redo().unwrap();
+		assert_eq!(*crossings.lock().unwrap(), [3, 5, 3]);
+	}
+
+	#[rstest]
+	fn on_evict_fires_for_trimmed_and_discarded_items() {
+		let evicted = Arc::new(Mutex::new(vec![]));
+		let mut history = History::new();
+		history.limit = NonZeroUsize::new(3);
+
+		let evicted_by_hook = Arc::clone(&evicted);
+		history.set_on_evict(Box::new(move |item: &u32| {
+			evicted_by_hook.lock().unwrap().push(*item);
+		}));
+
+		history.extend([0, 1, 2, 3, 4]);
+		assert_eq!(*evicted.lock().unwrap(), [0, 1]);
+
+		history.undo().unwrap();
+		history.undo().unwrap();
+		history.push(5);
+		assert_eq!(*evicted.lock().unwrap(), [0, 1, 3, 4]);
+
+		history.replace_committed(vec![6, 7, 8, 9]);
+		assert_eq!(*evicted.lock().unwrap(), [0, 1, 3, 4, 6]);
+
+		history.undo().unwrap();
+		history.replace_committed(vec![10]);
+		assert_eq!(*evicted.lock().unwrap(), [0, 1, 3, 4, 6]);
+
+		history.clear();
+		history.clear_on_evict();
+		history.extend([6, 7, 8, 9]);
+		assert_eq!(*evicted.lock().unwrap(), [0, 1, 3, 4, 6]);
+	}
+}
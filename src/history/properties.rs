@@ -0,0 +1,194 @@
+//! Property tests for [`History`], checking that its invariants hold across random sequences of
+//! actions.
+
+use core::num::NonZeroUsize;
+
+use proptest::{collection::vec, option, prelude::*};
+
+use super::History;
+
+/// An action which can be performed on a [`History`].
+#[derive(Clone, Debug)]
+enum Action {
+	/// Calls [`History::push()`].
+	Push(u8),
+	/// Calls [`History::extend()`].
+	Extend(Vec<u8>),
+	/// Calls [`History::undo()`].
+	Undo,
+	/// Calls [`History::redo()`].
+	Redo,
+	/// Calls [`History::clear()`].
+	Clear,
+	/// Calls [`History::set_limit()`].
+	SetLimit(Option<NonZeroUsize>),
+}
+
+/// Generates a history limit, which is usually small so that it's often reached.
+fn limit() -> impl Strategy<Value = Option<NonZeroUsize>> {
+	option::of((1..8_usize).prop_map(|limit| NonZeroUsize::new(limit).unwrap()))
+}
+
+/// Generates a single action, weighted towards those which change the history's length.
+fn action() -> impl Strategy<Value = Action> {
+	prop_oneof![
+		4 => any::<u8>().prop_map(Action::Push),
+		1 => vec(any::<u8>(), 0..6).prop_map(Action::Extend),
+		3 => Just(Action::Undo),
+		3 => Just(Action::Redo),
+		1 => Just(Action::Clear),
+		1 => limit().prop_map(Action::SetLimit),
+	]
+}
+
+/// A simple model of a history, which [`History`] is checked against.
+#[derive(Default)]
+struct Model {
+	/// The committed items, from oldest to newest.
+	committed: Vec<u8>,
+	/// The undone items, from most-recently undone to least-recently undone.
+	undone: Vec<u8>,
+	/// The history limit.
+	limit: Option<NonZeroUsize>,
+}
+
+impl Model {
+	/// Removes the oldest committed items until there are no more than `limit - plus` of them.
+	fn trim(&mut self, plus: usize) {
+		if let Some(limit) = self.limit {
+			let excess = (self.committed.len() + plus).saturating_sub(limit.get());
+			self.committed.drain(..excess);
+		}
+	}
+
+	/// Performs `action` on this model.
+	fn perform(&mut self, action: &Action) {
+		match action {
+			Action::Push(item) => {
+				self.trim(1);
+				self.committed.push(*item);
+				self.undone.clear();
+			}
+			Action::Extend(items) => {
+				for item in items {
+					self.perform(&Action::Push(*item));
+				}
+				self.undone.clear();
+			}
+			Action::Undo => {
+				if let Some(item) = self.committed.pop() {
+					self.undone.insert(0, item);
+				}
+			}
+			Action::Redo => {
+				if !self.undone.is_empty() {
+					self.trim(1);
+					self.committed.push(self.undone.remove(0));
+				}
+			}
+			Action::Clear => {
+				self.committed.clear();
+				self.undone.clear();
+			}
+			Action::SetLimit(limit) => {
+				self.limit = *limit;
+				self.trim(0);
+			}
+		}
+	}
+}
+
+/// Performs `action` on `history`.
+fn perform(history: &mut History<u8>, action: &Action) {
+	match action {
+		Action::Push(item) => history.push(*item),
+		Action::Extend(items) => history.extend(items.iter().copied()),
+		Action::Undo => {
+			let _ = history.undo();
+		}
+		Action::Redo => {
+			let _ = history.redo();
+		}
+		Action::Clear => history.clear(),
+		Action::SetLimit(limit) => history.set_limit(*limit),
+	}
+}
+
+proptest! {
+	#[test]
+	fn committed_never_exceeds_limit(actions in vec(action(), 0..64)) {
+		let mut history = History::new();
+		for action in &actions {
+			perform(&mut history, action);
+			if let Some(limit) = history.limit {
+				prop_assert!(history.iter_committed().len() <= limit.get());
+			}
+		}
+	}
+
+	#[test]
+	fn iter_covers_committed_and_undone(actions in vec(action(), 0..64)) {
+		let mut history = History::new();
+		for action in &actions {
+			perform(&mut history, action);
+			let committed = history.iter_committed().len();
+			let undone = history.iter_undone().len();
+			prop_assert_eq!(history.iter().count(), committed + undone);
+			prop_assert_eq!(history.split_index(), committed);
+		}
+	}
+
+	#[test]
+	fn undo_then_redo_is_identity(actions in vec(action(), 0..64)) {
+		let mut history = History::new();
+		for action in &actions {
+			perform(&mut history, action);
+		}
+
+		let before = history.clone();
+		if let Ok(&mut undone) = history.undo() {
+			prop_assert_eq!(history.redo().copied(), Ok(undone));
+		}
+		prop_assert_eq!(history, before);
+	}
+
+	#[test]
+	fn push_clears_undone(actions in vec(action(), 0..64), item in any::<u8>()) {
+		let mut history = History::new();
+		for action in &actions {
+			perform(&mut history, action);
+		}
+
+		history.push(item);
+		prop_assert_eq!(history.iter_undone().len(), 0);
+		prop_assert_eq!(history.iter_committed().next_back(), Some(&item));
+	}
+
+	#[test]
+	fn matches_model(actions in vec(action(), 0..64)) {
+		let mut history = History::new();
+		let mut model = Model::default();
+		for action in &actions {
+			perform(&mut history, action);
+			model.perform(action);
+			prop_assert!(history.iter_committed().eq(&model.committed));
+			prop_assert!(history.iter_undone().eq(&model.undone));
+		}
+	}
+
+	#[test]
+	fn undo_all_then_redo_all_restores_items(actions in vec(action(), 0..64)) {
+		let mut history = History::new();
+		for action in &actions {
+			perform(&mut history, action);
+		}
+
+		// Redoing everything may push the oldest items past the limit, in which case they're lost.
+		let items: Vec<u8> = history.iter().copied().collect();
+		let kept = history.limit.map_or(items.len(), |limit| limit.get().min(items.len()));
+		while history.undo().is_ok() {}
+		prop_assert_eq!(history.iter_committed().len(), 0);
+		while history.redo().is_ok() {}
+		prop_assert!(history.iter_committed().eq(&items[items.len() - kept..]));
+	}
+}
@@ -0,0 +1,458 @@
+//! Saving and loading histories of reflected [`Operation`]s as RON.
+
+use core::fmt::{Formatter, Result as FmtResult};
+
+use bevy_reflect::{
+	serde::{ReflectSerializer, TypedReflectDeserializer},
+	PartialReflect, Reflect, ReflectFromReflect, TypeRegistry,
+};
+use ron::{error::SpannedError, ser::PrettyConfig, Options};
+use serde::{
+	de::{self, DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor},
+	ser::{self, SerializeMap, SerializeSeq, SerializeStruct},
+	Deserializer, Serialize, Serializer,
+};
+
+use super::History;
+use crate::{
+	common_operations::PlaceholderOperation,
+	operation::{Details, Operation, ReflectOperation},
+};
+
+/// The fields of a serialized [`History`].
+const FIELDS: &[&str] = &["committed", "undone", "limit", "warn_threshold"];
+
+/// The key used in place of a type path for a serialized [`PlaceholderOperation`].
+const PLACEHOLDER_KEY: &str = "placeholder";
+
+impl History<Box<dyn Operation>> {
+	/// Serializes this history to RON, using `registry` to serialize each operation.
+	///
+	/// Committed operations are saved from oldest to newest, followed by undone operations in the
+	/// order they would be redone. The history limit and warning threshold are saved as well, but
+	/// any registered hooks are not.
+	///
+	/// # Errors
+	/// Returns an error if any operation does not support reflection (see
+	/// [`Operation::as_reflect()`]), or if any operation fails to serialize.
+	///
+	/// # See Also
+	/// * [`Self::to_ron_lossy()`] - Saves placeholders for operations which can't be serialized,
+	///   rather than failing.
+	pub fn to_ron(&self, registry: &TypeRegistry) -> Result<String, ron::Error> {
+		let serializer = HistorySerializer {
+			history: self,
+			registry,
+			lossy: false,
+		};
+		ron::ser::to_string_pretty(&serializer, PrettyConfig::default())
+	}
+
+	/// Serializes this history to RON, as with [`Self::to_ron()`], but saves a placeholder for any
+	/// operation which could not be loaded again - that is, any operation which does not support
+	/// reflection, or whose type is not registered in `registry` with `#[reflect(Operation)]`.
+	///
+	/// This is lossy. A placeholder only keeps the operation's [`Details::name`], and is loaded as
+	/// a [`PlaceholderOperation`], which can't be redone, and does nothing when undone. This is
+	/// intended for keeping a history around for display, such as in a bug report.
+	///
+	/// # Errors
+	/// Returns an error if any operation fails to serialize.
+	pub fn to_ron_lossy(&self, registry: &TypeRegistry) -> Result<String, ron::Error> {
+		let serializer = HistorySerializer {
+			history: self,
+			registry,
+			lossy: true,
+		};
+		ron::ser::to_string_pretty(&serializer, PrettyConfig::default())
+	}
+
+	/// Deserializes a history from RON produced by [`Self::to_ron()`], using `registry` to
+	/// deserialize each operation.
+	///
+	/// Every operation type in the history must be registered in `registry` with
+	/// `#[reflect(Operation)]`. Placeholders saved by [`Self::to_ron_lossy()`] are loaded as
+	/// [`PlaceholderOperation`]s. As with [`UndoRedo::with_history()`], the loaded operations are
+	/// assumed to be in the state the history says they're in.
+	///
+	/// # Errors
+	/// Returns an error if `ron` is malformed, or if any operation fails to deserialize or is not
+	/// registered as an [`Operation`].
+	///
+	/// [`UndoRedo::with_history()`]: crate::undoredo::UndoRedo::with_history()
+	pub fn from_ron(ron: &str, registry: &TypeRegistry) -> Result<Self, SpannedError> {
+		Options::default().from_str_seed(ron, HistoryDeserializer { registry })
+	}
+}
+
+/// Serializes a [`History`] of reflected operations.
+struct HistorySerializer<'a> {
+	/// The history to serialize.
+	history: &'a History<Box<dyn Operation>>,
+	/// The registry used to serialize each operation.
+	registry: &'a TypeRegistry,
+	/// Whether to save placeholders for operations which can't be serialized.
+	lossy: bool,
+}
+
+impl Serialize for HistorySerializer<'_> {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		let committed = self.history.committed.iter().map(AsRef::as_ref).collect();
+		let undone = self
+			.history
+			.undone
+			.iter()
+			.rev()
+			.map(AsRef::as_ref)
+			.collect();
+
+		let mut state = serializer.serialize_struct("History", FIELDS.len())?;
+		state.serialize_field(
+			"committed",
+			&OperationsSerializer {
+				operations: committed,
+				registry: self.registry,
+				lossy: self.lossy,
+			},
+		)?;
+		state.serialize_field(
+			"undone",
+			&OperationsSerializer {
+				operations: undone,
+				registry: self.registry,
+				lossy: self.lossy,
+			},
+		)?;
+		state.serialize_field("limit", &self.history.limit)?;
+		state.serialize_field("warn_threshold", &self.history.warn_threshold)?;
+		state.end()
+	}
+}
+
+/// Serializes a list of reflected operations.
+struct OperationsSerializer<'a> {
+	/// The operations to serialize, in order.
+	operations: Vec<&'a dyn Operation>,
+	/// The registry used to serialize each operation.
+	registry: &'a TypeRegistry,
+	/// Whether to save placeholders for operations which can't be serialized.
+	lossy: bool,
+}
+
+impl OperationsSerializer<'_> {
+	/// Returns `true` if `reflected` is registered such that it can be loaded as an operation.
+	fn is_loadable(&self, reflected: &dyn Reflect) -> bool {
+		self.registry
+			.get(reflected.type_id())
+			.is_some_and(|registration| {
+				registration.data::<ReflectOperation>().is_some()
+					&& registration.data::<ReflectFromReflect>().is_some()
+			})
+	}
+}
+
+impl Serialize for OperationsSerializer<'_> {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		let mut seq = serializer.serialize_seq(Some(self.operations.len()))?;
+		for operation in &self.operations {
+			let reflected = operation
+				.as_reflect()
+				.filter(|reflected| !self.lossy || self.is_loadable(*reflected));
+			match reflected {
+				Some(reflected) => seq.serialize_element(&ReflectSerializer::new(
+					reflected.as_partial_reflect(),
+					self.registry,
+				))?,
+				// Placeholders are always saved as such, so that they survive being loaded and
+				// saved again.
+				None if self.lossy || operation.is::<PlaceholderOperation>() => {
+					seq.serialize_element(&PlaceholderSerializer {
+						details: operation.details(),
+					})?;
+				}
+				None => {
+					return Err(ser::Error::custom(format_args!(
+						"operation `{}` does not support reflection",
+						operation.details().name
+					)));
+				}
+			}
+		}
+		seq.end()
+	}
+}
+
+/// Serializes a [`PlaceholderOperation`] in place of an operation which can't be serialized.
+struct PlaceholderSerializer {
+	/// The details of the operation being replaced.
+	details: Details,
+}
+
+impl Serialize for PlaceholderSerializer {
+	fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		let mut map = serializer.serialize_map(Some(1))?;
+		map.serialize_entry(PLACEHOLDER_KEY, &self.details.name)?;
+		map.end()
+	}
+}
+
+/// Deserializes a [`History`] of reflected operations.
+struct HistoryDeserializer<'a> {
+	/// The registry used to deserialize each operation.
+	registry: &'a TypeRegistry,
+}
+
+impl<'de> DeserializeSeed<'de> for HistoryDeserializer<'_> {
+	type Value = History<Box<dyn Operation>>;
+
+	fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+		deserializer.deserialize_struct("History", FIELDS, self)
+	}
+}
+
+impl<'de> Visitor<'de> for HistoryDeserializer<'_> {
+	type Value = History<Box<dyn Operation>>;
+
+	fn expecting(&self, formatter: &mut Formatter<'_>) -> FmtResult {
+		formatter.write_str("a history of reflected operations")
+	}
+
+	fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+		let mut history = History::new();
+		let operations = || OperationsDeserializer {
+			registry: self.registry,
+		};
+
+		// RON only allows field names to be deserialized as borrowed strings.
+		while let Some(key) = map.next_key::<&str>()? {
+			match key {
+				"committed" => history.committed = map.next_value_seed(operations())?.into(),
+				"undone" => {
+					// Undone operations are saved in redo order, which is the reverse of how
+					// they're stored.
+					history.undone = map.next_value_seed(operations())?;
+					history.undone.reverse();
+				}
+				"limit" => history.limit = map.next_value()?,
+				"warn_threshold" => history.warn_threshold = map.next_value()?,
+				other => return Err(de::Error::unknown_field(other, FIELDS)),
+			}
+		}
+
+		Ok(history)
+	}
+}
+
+/// Deserializes a list of reflected operations.
+struct OperationsDeserializer<'a> {
+	/// The registry used to deserialize each operation.
+	registry: &'a TypeRegistry,
+}
+
+impl<'de> DeserializeSeed<'de> for OperationsDeserializer<'_> {
+	type Value = Vec<Box<dyn Operation>>;
+
+	fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+		deserializer.deserialize_seq(self)
+	}
+}
+
+impl<'de> Visitor<'de> for OperationsDeserializer<'_> {
+	type Value = Vec<Box<dyn Operation>>;
+
+	fn expecting(&self, formatter: &mut Formatter<'_>) -> FmtResult {
+		formatter.write_str("a list of reflected operations")
+	}
+
+	fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+		let mut operations = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+		let operation = || OperationDeserializer {
+			registry: self.registry,
+		};
+		while let Some(operation) = seq.next_element_seed(operation())? {
+			operations.push(operation);
+		}
+		Ok(operations)
+	}
+}
+
+/// Deserializes a single reflected operation, or a placeholder saved in place of one.
+///
+/// Operations are saved as a map with a single entry, from the operation's type path to its
+/// reflected value, as done by [`ReflectSerializer`]. Placeholders use [`PLACEHOLDER_KEY`] in
+/// place of the type path, mapped to the operation's name.
+struct OperationDeserializer<'a> {
+	/// The registry used to deserialize the operation.
+	registry: &'a TypeRegistry,
+}
+
+impl<'de> DeserializeSeed<'de> for OperationDeserializer<'_> {
+	type Value = Box<dyn Operation>;
+
+	fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+		deserializer.deserialize_map(self)
+	}
+}
+
+impl<'de> Visitor<'de> for OperationDeserializer<'_> {
+	type Value = Box<dyn Operation>;
+
+	fn expecting(&self, formatter: &mut Formatter<'_>) -> FmtResult {
+		formatter.write_str("a reflected operation, or a placeholder")
+	}
+
+	fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+		let key = map
+			.next_key::<String>()?
+			.ok_or_else(|| de::Error::invalid_length(0, &self))?;
+
+		let operation: Box<dyn Operation> = if key == PLACEHOLDER_KEY {
+			let name = map.next_value()?;
+			Box::new(PlaceholderOperation::new(Details { name }))
+		} else {
+			let registration = self
+				.registry
+				.get_with_type_path(&key)
+				.ok_or_else(|| de::Error::custom(format_args!("`{key}` is not registered")))?;
+			let reflected =
+				map.next_value_seed(TypedReflectDeserializer::new(registration, self.registry))?;
+			self::into_operation(&*reflected, self.registry).map_err(de::Error::custom)?
+		};
+
+		if map.next_key::<IgnoredAny>()?.is_some() {
+			return Err(de::Error::invalid_length(2, &self));
+		}
+		Ok(operation)
+	}
+}
+
+/// Converts a deserialized reflected value into the concrete [`Operation`] it represents.
+fn into_operation(
+	reflected: &dyn PartialReflect,
+	registry: &TypeRegistry,
+) -> Result<Box<dyn Operation>, String> {
+	let type_info = reflected
+		.get_represented_type_info()
+		.ok_or("deserialized operation has no type information")?;
+	let type_path = type_info.type_path();
+	let registration = registry
+		.get(type_info.type_id())
+		.ok_or_else(|| format!("`{type_path}` is not registered"))?;
+
+	let from_reflect = registration
+		.data::<ReflectFromReflect>()
+		.ok_or_else(|| format!("`{type_path}` is not registered with `FromReflect`"))?;
+	let reflect_operation = registration
+		.data::<ReflectOperation>()
+		.ok_or_else(|| format!("`{type_path}` is not registered with `#[reflect(Operation)]`"))?;
+
+	let concrete = from_reflect
+		.from_reflect(reflected)
+		.ok_or_else(|| format!("`{type_path}` could not be converted from its reflected form"))?;
+	reflect_operation
+		.get_boxed(concrete)
+		.map_err(|_| format!("`{type_path}` does not implement `Operation`"))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use bevy_ecs::system::Commands;
+	use bevy_reflect::Reflect;
+	use core::num::NonZeroUsize;
+	use rstest::rstest;
+
+	#[derive(Debug, PartialEq, Reflect)]
+	#[reflect(Operation)]
+	struct MoveBy {
+		x: i32,
+		y: i32,
+	}
+
+	impl Operation for MoveBy {
+		fn as_reflect(&self) -> Option<&dyn Reflect> {
+			Some(self)
+		}
+
+		fn apply(&mut self, _commands: &mut Commands) {}
+
+		fn undo(&self, _commands: &mut Commands) {}
+	}
+
+	/// Reads the `x` field of each operation in `operations`.
+	fn xs<'a>(operations: impl Iterator<Item = &'a Box<dyn Operation>>) -> Vec<i32> {
+		operations
+			.map(|operation| {
+				let reflected = operation.as_reflect().unwrap();
+				reflected.downcast_ref::<MoveBy>().unwrap().x
+			})
+			.collect()
+	}
+
+	#[rstest]
+	fn round_trips_through_registry() {
+		let mut registry = TypeRegistry::new();
+		registry.register::<MoveBy>();
+
+		let mut history: History<Box<dyn Operation>> =
+			(0..4).map(|x| Box::new(MoveBy { x, y: -x }) as _).collect();
+		history.limit = NonZeroUsize::new(10);
+		history.undo().unwrap();
+		history.undo().unwrap();
+
+		let ron = history.to_ron(&registry).unwrap();
+		let loaded = History::from_ron(&ron, &registry).unwrap();
+
+		assert_eq!(xs(loaded.iter_committed()), [0, 1]);
+		assert_eq!(xs(loaded.iter_undone()), [2, 3]);
+		assert_eq!(loaded.limit, history.limit);
+	}
+
+	/// An operation which does not support reflection.
+	struct Opaque;
+
+	impl Operation for Opaque {
+		fn apply(&mut self, _commands: &mut Commands) {}
+
+		fn undo(&self, _commands: &mut Commands) {}
+	}
+
+	#[rstest]
+	fn lossy_saves_placeholders_for_unserializable_operations() {
+		let mut registry = TypeRegistry::new();
+		registry.register::<MoveBy>();
+
+		let mut history: History<Box<dyn Operation>> = History::new();
+		history.push(Box::new(MoveBy { x: 1, y: 2 }));
+		history.push(Box::new(Opaque));
+		history.push(Box::new(MoveBy { x: 3, y: 4 }));
+		history.undo().unwrap();
+		history.undo().unwrap();
+		assert!(history.to_ron(&registry).is_err());
+
+		let ron = history.to_ron_lossy(&registry).unwrap();
+		let loaded = History::from_ron(&ron, &registry).unwrap();
+		let names: Vec<_> = loaded
+			.iter()
+			.map(|operation| operation.details().name)
+			.collect();
+		assert_eq!(names, ["MoveBy", "Opaque", "MoveBy"]);
+		assert!(loaded.peek(1).unwrap().is::<PlaceholderOperation>());
+		assert!(loaded.peek(2).unwrap().is::<MoveBy>());
+
+		// Placeholders are saved as placeholders again, even without `to_ron_lossy()`.
+		let resaved = History::from_ron(&loaded.to_ron(&registry).unwrap(), &registry).unwrap();
+		assert!(resaved.peek(1).unwrap().is::<PlaceholderOperation>());
+	}
+
+	#[rstest]
+	fn unregistered_operations_fail_to_load() {
+		let mut registry = TypeRegistry::new();
+		registry.register::<MoveBy>();
+		let history: History<Box<dyn Operation>> =
+			core::iter::once(Box::new(MoveBy { x: 1, y: 2 }) as _).collect();
+		let ron = history.to_ron(&registry).unwrap();
+
+		assert!(History::from_ron(&ron, &TypeRegistry::new()).is_err());
+	}
+}
@@ -0,0 +1,15 @@
+//! Traits for estimating the memory used by items in a [`History`].
+//!
+//! [`History`]: super::History
+
+/// A type which can estimate how much heap memory it owns.
+///
+/// This is used by [`History::deep_memory_usage()`] to account for memory which isn't stored
+/// inline in the history itself, such as the contents of a `Vec` held by an item.
+///
+/// [`History::deep_memory_usage()`]: super::History::deep_memory_usage()
+pub trait HeapSize {
+	/// Returns an estimate of the number of bytes of heap memory owned by this value. This should
+	/// not include `size_of::<Self>()`, as that is already accounted for by the history.
+	fn heap_size(&self) -> usize;
+}
@@ -0,0 +1,256 @@
+//! Keyboard shortcuts for performing undos and redos. This module requires the `input` feature.
+//!
+//! This module provides [`UndoRedoKeyBindings`], a resource describing which key chords should
+//! trigger an undo or redo, and [`handle_undo_redo_keys`], a system which reads
+//! [`ButtonInput<KeyCode>`] and performs the matching action.
+//!
+//! Neither is added by [`UndoRedoPlugin`](crate::UndoRedoPlugin); to use them, insert (or
+//! initialize) the resource and add the system yourself, optionally placing it in
+//! [`UndoRedoSystems`](crate::plugin::UndoRedoSystems) so that it shares the plugin's run
+//! conditions.
+
+use bevy_ecs::system::{Commands, Res, Resource};
+use bevy_input::{keyboard::KeyCode, ButtonInput};
+
+use crate::extensions::CommandsUndoRedoExt;
+
+/// The modifier keys which may be held as part of a [`KeyChord`].
+///
+/// Both the left and right variants of each modifier are accepted.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Modifiers {
+	/// Either Control key.
+	pub ctrl: bool,
+	/// Either Shift key.
+	pub shift: bool,
+	/// Either Alt (or Option) key.
+	pub alt: bool,
+	/// Either Super key. This is the Command key on macOS, and the Windows key on Windows.
+	pub super_key: bool,
+}
+
+impl Modifiers {
+	/// No modifiers.
+	pub const NONE: Self = Self {
+		ctrl: false,
+		shift: false,
+		alt: false,
+		super_key: false,
+	};
+
+	/// Only Control.
+	pub const CTRL: Self = Self {
+		ctrl: true,
+		..Self::NONE
+	};
+
+	/// Only Super.
+	pub const SUPER: Self = Self {
+		super_key: true,
+		..Self::NONE
+	};
+
+	/// Adds Shift to this set of modifiers.
+	#[must_use]
+	pub const fn with_shift(self) -> Self {
+		Self {
+			shift: true,
+			..self
+		}
+	}
+
+	/// Adds Alt to this set of modifiers.
+	#[must_use]
+	pub const fn with_alt(self) -> Self {
+		Self { alt: true, ..self }
+	}
+
+	/// Reads which modifiers are currently held.
+	#[must_use]
+	pub fn pressed(input: &ButtonInput<KeyCode>) -> Self {
+		Self {
+			ctrl: input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]),
+			shift: input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]),
+			alt: input.any_pressed([KeyCode::AltLeft, KeyCode::AltRight]),
+			super_key: input.any_pressed([KeyCode::SuperLeft, KeyCode::SuperRight]),
+		}
+	}
+}
+
+/// A key, pressed while holding an exact set of [`Modifiers`].
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct KeyChord {
+	/// The key which triggers this chord when it is pressed.
+	pub key: KeyCode,
+	/// The modifiers which must be held when `key` is pressed. Modifiers not listed here must not
+	/// be held, so that (for example) Ctrl+Z does not also match Ctrl+Shift+Z.
+	pub modifiers: Modifiers,
+}
+
+impl KeyChord {
+	/// Creates a new `KeyChord`.
+	#[must_use]
+	pub const fn new(modifiers: Modifiers, key: KeyCode) -> Self {
+		Self { key, modifiers }
+	}
+
+	/// Returns whether this chord was just pressed.
+	#[must_use]
+	pub fn just_pressed(&self, input: &ButtonInput<KeyCode>) -> bool {
+		input.just_pressed(self.key) && Modifiers::pressed(input) == self.modifiers
+	}
+}
+
+/// A [`Resource`] listing the [`KeyChord`]s which trigger an undo or a redo.
+///
+/// Any number of chords may be bound to each action. The [`Default`] bindings follow the
+/// conventions of the target platform:
+///
+/// | Platform | Undo   | Redo                    |
+/// |----------|--------|-------------------------|
+/// | macOS    | Cmd+Z  | Cmd+Shift+Z             |
+/// | Other    | Ctrl+Z | Ctrl+Y, Ctrl+Shift+Z    |
+#[derive(Clone, Debug, PartialEq, Eq, Resource)]
+pub struct UndoRedoKeyBindings {
+	/// Chords which perform an undo.
+	pub undo: Vec<KeyChord>,
+	/// Chords which perform a redo.
+	pub redo: Vec<KeyChord>,
+}
+
+impl UndoRedoKeyBindings {
+	/// Creates a new `UndoRedoKeyBindings` with nothing bound.
+	#[must_use]
+	pub const fn empty() -> Self {
+		Self {
+			undo: Vec::new(),
+			redo: Vec::new(),
+		}
+	}
+
+	/// The default bindings on macOS.
+	#[must_use]
+	pub fn macos() -> Self {
+		Self {
+			undo: vec![KeyChord::new(Modifiers::SUPER, KeyCode::KeyZ)],
+			redo: vec![KeyChord::new(Modifiers::SUPER.with_shift(), KeyCode::KeyZ)],
+		}
+	}
+
+	/// The default bindings on platforms other than macOS.
+	#[must_use]
+	pub fn standard() -> Self {
+		Self {
+			undo: vec![KeyChord::new(Modifiers::CTRL, KeyCode::KeyZ)],
+			redo: vec![
+				KeyChord::new(Modifiers::CTRL, KeyCode::KeyY),
+				KeyChord::new(Modifiers::CTRL.with_shift(), KeyCode::KeyZ),
+			],
+		}
+	}
+}
+
+impl Default for UndoRedoKeyBindings {
+	fn default() -> Self {
+		if cfg!(target_os = "macos") {
+			Self::macos()
+		} else {
+			Self::standard()
+		}
+	}
+}
+
+/// A system which performs an undo or redo when one of the chords in [`UndoRedoKeyBindings`] is
+/// pressed.
+///
+/// If both an undo and a redo chord were pressed in the same frame, only the undo is performed.
+pub fn handle_undo_redo_keys(
+	input: Res<ButtonInput<KeyCode>>,
+	bindings: Res<UndoRedoKeyBindings>,
+	mut commands: Commands,
+) {
+	if bindings.undo.iter().any(|chord| chord.just_pressed(&input)) {
+		commands.undo();
+	} else if bindings.redo.iter().any(|chord| chord.just_pressed(&input)) {
+		commands.redo();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		test_fixtures::{AddToCounter, Counter},
+		UndoRedo,
+	};
+	use bevy_ecs::{
+		system::RunSystemOnce,
+		world::{Mut, World},
+	};
+	use rstest::rstest;
+
+	/// Sets up a world where one operation has been applied and another has been undone, so that
+	/// both an undo and a redo would change the counter.
+	fn world_with(bindings: UndoRedoKeyBindings) -> World {
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		world.init_resource::<UndoRedo>();
+		world.init_resource::<ButtonInput<KeyCode>>();
+		world.insert_resource(bindings);
+		world.resource_scope(|world, mut undoredo: Mut<UndoRedo>| {
+			let mut commands = world.commands();
+			undoredo
+				.push_and_apply(AddToCounter(1), &mut commands)
+				.unwrap();
+			undoredo
+				.push_and_apply(AddToCounter(1), &mut commands)
+				.unwrap();
+			undoredo.undo(&mut commands).unwrap();
+		});
+		world.flush();
+		assert_eq!(world.resource::<Counter>().0, 1);
+		world
+	}
+
+	fn press(world: &mut World, keys: &[KeyCode]) -> i32 {
+		let mut input = world.resource_mut::<ButtonInput<KeyCode>>();
+		for &key in keys {
+			input.press(key);
+		}
+		world.run_system_once(handle_undo_redo_keys).unwrap();
+		world.resource::<Counter>().0
+	}
+
+	#[rstest]
+	#[case::ctrl_z(&[KeyCode::ControlLeft, KeyCode::KeyZ], 0)]
+	#[case::ctrl_y(&[KeyCode::ControlRight, KeyCode::KeyY], 2)]
+	#[case::ctrl_shift_z(&[KeyCode::ControlLeft, KeyCode::ShiftLeft, KeyCode::KeyZ], 2)]
+	#[case::bare_z(&[KeyCode::KeyZ], 1)]
+	#[case::ctrl_alt_z(&[KeyCode::ControlLeft, KeyCode::AltLeft, KeyCode::KeyZ], 1)]
+	#[case::cmd_z(&[KeyCode::SuperLeft, KeyCode::KeyZ], 1)]
+	#[case::ctrl_x(&[KeyCode::ControlLeft, KeyCode::KeyX], 1)]
+	fn standard_bindings(#[case] keys: &[KeyCode], #[case] expected: i32) {
+		let mut world = world_with(UndoRedoKeyBindings::standard());
+		assert_eq!(press(&mut world, keys), expected);
+	}
+
+	#[rstest]
+	#[case::cmd_z(&[KeyCode::SuperLeft, KeyCode::KeyZ], 0)]
+	#[case::cmd_shift_z(&[KeyCode::SuperRight, KeyCode::ShiftRight, KeyCode::KeyZ], 2)]
+	#[case::ctrl_z(&[KeyCode::ControlLeft, KeyCode::KeyZ], 1)]
+	#[case::cmd_y(&[KeyCode::SuperLeft, KeyCode::KeyY], 1)]
+	fn macos_bindings(#[case] keys: &[KeyCode], #[case] expected: i32) {
+		let mut world = world_with(UndoRedoKeyBindings::macos());
+		assert_eq!(press(&mut world, keys), expected);
+	}
+
+	#[rstest]
+	fn held_chord_does_not_repeat() {
+		let mut world = world_with(UndoRedoKeyBindings::standard());
+		assert_eq!(press(&mut world, &[KeyCode::ControlLeft, KeyCode::KeyZ]), 0);
+		world.resource_mut::<ButtonInput<KeyCode>>().clear();
+		world.run_system_once(handle_undo_redo_keys).unwrap();
+		assert_eq!(world.resource::<Counter>().0, 0);
+	}
+}
@@ -1,10 +1,20 @@
 //! `bevy_undo_redo` is an implementation of an undo/redo system for the Bevy game engine.
 
+pub mod coalescing;
 pub mod common_operations;
 pub(crate) mod error;
 pub mod extensions;
 pub mod history;
+#[cfg(feature = "input")]
+pub mod input;
 pub mod operation;
+pub mod plugin;
+pub mod registry;
+#[cfg(test)]
+mod test_fixtures;
 pub mod undoredo;
 
-pub use crate::{error::Error, history::History, operation::Operation, undoredo::UndoRedo};
+pub use crate::{
+	error::Error, history::History, operation::Operation, plugin::UndoRedoPlugin,
+	undoredo::UndoRedo,
+};
@@ -1,24 +1,328 @@
 //! Types and traits for implementing and handling [`Operation`]s.
 
-use bevy_ecs::system::Commands;
+use bevy_ecs::{
+	entity::Entity,
+	system::Commands,
+	world::{CommandQueue, World},
+};
+use core::any::Any;
+
+use disqualified::ShortName;
+
+use crate::{coalescing::CoalesceResult, common_operations::OperationGroup, error::Error};
 
 /// An action or sequence of commands which can later be undone.
 ///
 /// This can be thought of as an "undoable [`Command`]". In fact, in many cases, an `Operation` will
 /// itself also implement `Command`.
 ///
+/// # Zero-Sized Operations
+/// Operations are stored as `Box<dyn Operation>`, but boxing a zero-sized type does not allocate,
+/// so operations which hold no data (such as `struct ToggleGrid;`) only cost the size of the box
+/// itself to queue and store.
+///
+/// # Reflection
+/// With the `reflect` feature enabled, operations which implement [`Reflect`] can be saved and
+/// loaded through `History::to_ron()` and `History::from_ron()`. To support this, an operation must
+/// override `Operation::as_reflect()`, and be registered in the [`TypeRegistry`] with
+/// `#[reflect(Operation)]` (which requires `ReflectOperation` to be in scope).
+///
 /// [`Command`]: bevy_ecs::world::Command
-pub trait Operation: Send + Sync + 'static {
+/// [`Reflect`]: bevy_reflect::Reflect
+/// [`TypeRegistry`]: bevy_reflect::TypeRegistry
+#[cfg_attr(feature = "reflect", bevy_reflect::reflect_trait)]
+pub trait Operation: IntoDyn + Send + Sync + 'static {
 	/// Returns a list of details related to this operation.
-	fn details(&self) -> Details;
+	///
+	/// By default, the returned details are named after the operation's type, with any module paths
+	/// removed (i.e. `my_game::ops::MoveObject` becomes `MoveObject`).
+	fn details(&self) -> Details {
+		Details {
+			name: ShortName::of::<Self>().to_string(),
+		}
+	}
+
+	/// Returns the ID of the transaction this operation is part of, if any.
+	///
+	/// Consecutive operations sharing a transaction ID are treated as one logical action by
+	/// [`UndoRedo::undo_last_action()`], even though they're stored as separate history entries.
+	/// By default, operations are not part of any transaction.
+	///
+	/// [`UndoRedo::undo_last_action()`]: crate::undoredo::UndoRedo::undo_last_action()
+	fn transaction_id(&self) -> Option<u64> {
+		None
+	}
+
+	/// Returns a key describing which part of the World this operation affects, if any - for
+	/// example, the ID of the tilemap chunk containing the cell it paints.
+	///
+	/// When applied through [`UndoRedo::apply_queue()`], consecutive operations with the same
+	/// locality key are recorded together as one [`OperationGroup`], and so are undone together.
+	/// By default, operations have no locality key, and are always recorded on their own.
+	///
+	/// [`UndoRedo::apply_queue()`]: crate::undoredo::UndoRedo::apply_queue()
+	fn locality_key(&self) -> Option<u64> {
+		None
+	}
+
+	/// Returns the entities this operation affects, such as the entity it moves.
+	///
+	/// This is used by [`CoalesceKey::SameEntity`] to group consecutive operations affecting the
+	/// same entity. By default, operations affect no entities.
+	///
+	/// [`CoalesceKey::SameEntity`]: crate::coalescing::CoalesceKey::SameEntity
+	fn affected_entities(&self) -> Vec<Entity> {
+		Vec::new()
+	}
+
+	/// Returns an estimate of how costly this operation is to keep in the history, such as the
+	/// number of bytes of data it holds for undoing.
+	///
+	/// This is purely informational, and is used by methods such as
+	/// [`UndoRedo::next_undo_weight()`] - for example, to show how much memory an undo would free.
+	/// By default, operations have a weight of 0.
+	///
+	/// [`UndoRedo::next_undo_weight()`]: crate::undoredo::UndoRedo::next_undo_weight()
+	fn weight(&self) -> usize {
+		0
+	}
+
+	/// Returns `true` if this operation may be undone without asking the user first.
+	///
+	/// Destructive or expensive-to-reverse operations can return `false`, in which case
+	/// [`UndoRedo::undo()`] refuses to undo them, so that the app can ask the user to confirm
+	/// before calling [`UndoRedo::undo_confirmed()`]. By default, this returns `true`.
+	///
+	/// [`UndoRedo::undo()`]: crate::undoredo::UndoRedo::undo()
+	/// [`UndoRedo::undo_confirmed()`]: crate::undoredo::UndoRedo::undo_confirmed()
+	fn confirm_undo(&self) -> bool {
+		true
+	}
+
+	/// Returns `true` if applying this operation exactly reverses the effects of `other` - for
+	/// example, moving an object back by the distance `other` moved it.
+	///
+	/// When enabled through [`UndoRedo::set_cancel_inverses()`], an operation which inverts the
+	/// most recently applied operation cancels it out, and neither is recorded. By default,
+	/// operations never invert one another.
+	///
+	/// [`UndoRedo::set_cancel_inverses()`]: crate::undoredo::UndoRedo::set_cancel_inverses()
+	fn inverts(&self, other: &dyn Operation) -> bool {
+		let _ = other;
+		false
+	}
+
+	/// Returns this operation as a [`Reflect`] value, if it supports reflection.
+	///
+	/// By default, this returns `None`. Operations which implement `Reflect` should override this
+	/// to return `Some(self)`, allowing them to be saved through [`History::to_ron()`].
+	///
+	/// [`Reflect`]: bevy_reflect::Reflect
+	/// [`History::to_ron()`]: crate::history::History::to_ron()
+	#[cfg(feature = "reflect")]
+	fn as_reflect(&self) -> Option<&dyn bevy_reflect::Reflect> {
+		None
+	}
+
+	/// Checks whether this operation is valid, before it is queued or applied.
+	///
+	/// This allows operations which would misbehave when applied (for example, because they refer
+	/// to `Entity::PLACEHOLDER`) to be rejected early. By default, all operations are valid.
+	///
+	/// # Errors
+	/// Returns a description of why the operation is invalid.
+	fn validate(&self) -> Result<(), String> {
+		Ok(())
+	}
 
 	/// Queues up the commands needed to apply this operation to the World.
 	///
 	/// Operations get a mutable reference to themselves. This allows Operations to store some data
 	/// (such as an `Entity`) which can later be used for undoing the operation.
 	fn apply(&mut self, commands: &mut Commands);
+	/// Queues up the commands needed to apply this operation to the World, with access to an
+	/// [`OperationContext`] shared with any other operations being applied alongside this one.
+	///
+	/// By default, this ignores `context` and calls [`Self::apply()`]. Override this if your
+	/// operation needs to share data with (or receive data from) other operations, such as the
+	/// `Entity` of something it spawned.
+	///
+	/// Note that redoing an operation applies it on its own, with an empty context. Operations that
+	/// read from the context should store whatever they read, so that they can be redone later.
+	///
+	/// # Errors
+	/// By default, this never returns an error. Operations which override this may return an error
+	/// (usually [`Error::OperationFailed`]) if they cannot be applied, in which case they should not
+	/// have queued any commands. A failed operation is not added to the list of applied operations.
+	fn apply_in_context(
+		&mut self,
+		commands: &mut Commands,
+		context: &mut OperationContext,
+	) -> Result<(), Error> {
+		let _ = context;
+		self.apply(commands);
+		Ok(())
+	}
+
+	/// Applies this operation to `world` immediately.
+	///
+	/// By default, this calls [`Self::apply_in_context()`] with an empty context, then applies the
+	/// commands it queued to `world`. Override this if your operation needs to read from the World
+	/// while being applied - for example, to record a component's current value so that it can be
+	/// restored when undoing.
+	///
+	/// This is used by [`UndoRedo::push_and_apply_world()`]. Redoing an operation still goes
+	/// through [`Self::apply_in_context()`], so operations which override this should store
+	/// whatever they read, so that they can be redone later.
+	///
+	/// # Errors
+	/// The same as [`Self::apply_in_context()`]. A failed operation should not have modified
+	/// `world`.
+	///
+	/// [`UndoRedo::push_and_apply_world()`]: crate::undoredo::UndoRedo::push_and_apply_world()
+	fn apply_world(&mut self, world: &mut World) -> Result<(), Error> {
+		let mut queue = CommandQueue::default();
+		let result = self.apply_in_context(
+			&mut Commands::new(&mut queue, world),
+			&mut OperationContext::new(),
+		);
+		queue.apply(world);
+		result
+	}
+	/// Records the commands this operation would queue when applied into a fresh [`CommandQueue`],
+	/// without executing them.
+	///
+	/// This allows tooling to inspect (or defer) what an operation will do. Applying the returned
+	/// queue to `world` has the same effect as applying the operation directly.
+	///
+	/// By default, this calls [`Self::apply()`] with a temporary `Commands` backed by the returned
+	/// queue. As such, any data the operation stores about itself when applied (such as the
+	/// `Entity` it spawned) is still stored, and entities reserved by the commands will exist in
+	/// `world` as empty entities once it is flushed, even if the queue is discarded.
+	#[must_use]
+	fn preview_apply(&mut self, world: &World) -> CommandQueue {
+		let mut queue = CommandQueue::default();
+		self.apply(&mut Commands::new(&mut queue, world));
+		queue
+	}
+
 	/// Queues up the commands needed to undo this operation.
 	fn undo(&self, commands: &mut Commands);
+
+	/// Combines this operation and `next` into an [`OperationGroup`], named after this operation.
+	///
+	/// The group applies this operation and then `next`, and undoes them in reverse order. Calls can
+	/// be chained, such as `move_op.then(rename_op).then(recolor_op)`, though each call nests the
+	/// previous group inside a new one.
+	fn then<O: Operation>(self, next: O) -> OperationGroup
+	where
+		Self: Sized,
+	{
+		let mut group = OperationGroup::new(self.details());
+		group.push(self);
+		group.push(next);
+		group
+	}
+
+	/// Attempts to merge `next` into this operation, returning a single operation which covers
+	/// both.
+	///
+	/// This is used to merge consecutive operations into one undo step when no [`CoalescePolicy`]
+	/// is set on an [`UndoRedo`]. The merged operation may be of a different type than either
+	/// input (such as an [`OperationGroup`]). [`<dyn Operation>::downcast()`] can be used to check
+	/// whether `next` is of a type this operation can merge with.
+	///
+	/// As with [`CoalescePolicy::coalesce()`], both operations have already been applied by the
+	/// time this is called, and so the merged operation must not be applied again.
+	///
+	/// # Errors
+	/// Returns both operations, unmerged and in their original order, if they should not be merged.
+	/// By default, operations are never merged.
+	///
+	/// [`CoalescePolicy`]: crate::coalescing::CoalescePolicy
+	/// [`CoalescePolicy::coalesce()`]: crate::coalescing::CoalescePolicy::coalesce()
+	/// [`UndoRedo`]: crate::undoredo::UndoRedo
+	/// [`<dyn Operation>::downcast()`]: #method.downcast
+	fn merge_into(self: Box<Self>, next: Box<dyn Operation>) -> CoalesceResult {
+		Err((self.into_dyn(), next))
+	}
+}
+
+/// Conversions from an [`Operation`] to other trait objects.
+///
+/// This is automatically implemented for every `Operation`, and is used to implement
+/// [`<dyn Operation>::downcast()`] and the default [`Operation::merge_into()`].
+///
+/// [`<dyn Operation>::downcast()`]: trait.Operation.html#method.downcast
+pub trait IntoDyn: Any {
+	/// Returns this value as `&dyn Any`.
+	fn as_any(&self) -> &dyn Any;
+	/// Returns this value as `&mut dyn Any`.
+	fn as_any_mut(&mut self) -> &mut dyn Any;
+	/// Converts this boxed value into a `Box<dyn Any>`.
+	fn into_any(self: Box<Self>) -> Box<dyn Any>;
+	/// Converts this boxed value into a `Box<dyn Operation>`.
+	fn into_dyn(self: Box<Self>) -> Box<dyn Operation>;
+}
+
+impl<T: Operation> IntoDyn for T {
+	fn as_any(&self) -> &dyn Any {
+		self
+	}
+
+	fn as_any_mut(&mut self) -> &mut dyn Any {
+		self
+	}
+
+	fn into_any(self: Box<Self>) -> Box<dyn Any> {
+		self
+	}
+
+	fn into_dyn(self: Box<Self>) -> Box<dyn Operation> {
+		self
+	}
+}
+
+impl dyn Operation {
+	/// Returns `true` if this operation is of type `T`.
+	#[must_use]
+	pub fn is<T: Operation>(&self) -> bool {
+		self.as_any().is::<T>()
+	}
+
+	/// Returns a reference to this operation as type `T`, if it is of that type.
+	#[must_use]
+	pub fn downcast_ref<T: Operation>(&self) -> Option<&T> {
+		self.as_any().downcast_ref()
+	}
+
+	/// Returns a mutable reference to this operation as type `T`, if it is of that type.
+	#[must_use]
+	pub fn downcast_mut<T: Operation>(&mut self) -> Option<&mut T> {
+		self.as_any_mut().downcast_mut()
+	}
+
+	/// Attempts to convert this boxed operation into a `Box<T>`.
+	///
+	/// # Errors
+	/// Returns this operation unchanged if it is not of type `T`.
+	pub fn downcast<T: Operation>(self: Box<Self>) -> Result<Box<T>, Box<Self>> {
+		if self.is::<T>() {
+			// This can't fail, as we've just checked the type.
+			self.into_any().downcast().map_err(|_| unreachable!())
+		} else {
+			Err(self)
+		}
+	}
+}
+
+/// Returns `true` if `a` and `b` have equal [`Details`].
+///
+/// This is useful in tests, where operations themselves usually can't be compared.
+#[cfg(any(test, feature = "test-utils"))]
+#[must_use]
+pub fn details_eq(a: &dyn Operation, b: &dyn Operation) -> bool {
+	a.details() == b.details()
 }
 
 /// Data representing information about a operation or set of operations.
@@ -31,3 +335,301 @@ pub struct Details {
 	// TODO: Implement an interface to obtain this, rather than just exposing a public variable.
 	pub name: String,
 }
+
+/// Data shared between a set of operations that are applied together, such as by
+/// [`UndoRedo::apply_queue()`].
+///
+/// Operations can write data into the context when applied, and operations applied after them can
+/// read it back. This allows operations to depend on each other's results, even though their
+/// commands have not been applied to the World yet.
+///
+/// [`UndoRedo::apply_queue()`]: crate::undoredo::UndoRedo::apply_queue()
+#[expect(
+	clippy::module_name_repetitions,
+	reason = "This is a context shared between operations, and is named as such."
+)]
+#[derive(Clone, Debug, Default)]
+pub struct OperationContext {
+	/// Entities spawned by the operations applied so far, in the order they were recorded.
+	spawned_entities: Vec<Entity>,
+}
+
+impl OperationContext {
+	/// Creates a new, empty `OperationContext`.
+	#[must_use]
+	pub const fn new() -> Self {
+		Self {
+			spawned_entities: Vec::new(),
+		}
+	}
+
+	/// Records that an entity was spawned, so that operations applied later can use it.
+	pub fn push_spawned(&mut self, entity: Entity) {
+		self.spawned_entities.push(entity);
+	}
+
+	/// Returns all entities recorded through [`Self::push_spawned()`], in the order they were
+	/// recorded.
+	#[must_use]
+	pub fn spawned_entities(&self) -> &[Entity] {
+		&self.spawned_entities
+	}
+
+	/// Returns the most recently recorded spawned entity, if any.
+	#[must_use]
+	pub fn last_spawned(&self) -> Option<Entity> {
+		self.spawned_entities.last().copied()
+	}
+}
+
+/// Applies a clone of `operation` to `world`, then undoes it, returning `true` if every entity's
+/// components are back the way they were.
+///
+/// This is a self-test for catching operations whose undo is buggy during development, and so is
+/// only available in debug builds. As `world` is modified, it should be a scratch World set up
+/// for the test. Only components registered in the World's [`AppTypeRegistry`] with
+/// `#[reflect(Component)]` are compared, and entities must keep their IDs - an entity despawned
+/// and then respawned under a new ID is reported as a failure. Components which can't be compared
+/// through reflection are assumed to match. If the operation fails to apply, `false` is returned.
+///
+/// # Panics
+/// Panics if the World has no [`AppTypeRegistry`] resource.
+///
+/// [`AppTypeRegistry`]: bevy_ecs::reflect::AppTypeRegistry
+#[cfg(all(feature = "scene", debug_assertions))]
+#[must_use]
+pub fn verify_roundtrip<O: Operation + Clone>(operation: &O, world: &mut World) -> bool {
+	let before = self::capture_entities(world);
+
+	let mut operation = operation.clone();
+	if operation.apply_world(world).is_err() {
+		return false;
+	}
+	let mut queue = CommandQueue::default();
+	operation.undo(&mut Commands::new(&mut queue, world));
+	queue.apply(world);
+
+	let after = self::capture_entities(world);
+	before.entities.len() == after.entities.len()
+		&& before
+			.entities
+			.iter()
+			.zip(&after.entities)
+			.all(|(before, after)| {
+				before.entity == after.entity
+					&& before.components.len() == after.components.len()
+					&& before.components.iter().all(|component| {
+						after.components.iter().any(|other| {
+							component.reflect_type_path() == other.reflect_type_path()
+								&& component.reflect_partial_eq(&**other) != Some(false)
+						})
+					})
+			})
+}
+
+/// Captures the registered components of every entity in `world`, sorted by entity.
+#[cfg(all(feature = "scene", debug_assertions))]
+fn capture_entities(world: &World) -> bevy_scene::DynamicScene {
+	let entities = world.iter_entities().map(|entity| entity.id());
+	bevy_scene::DynamicSceneBuilder::from_world(world)
+		.extract_entities(entities)
+		.build()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_fixtures::{AddToCounter, Counter};
+	use bevy_ecs::{component::Component, query::With, system::Resource};
+	use rstest::rstest;
+
+	#[derive(Component)]
+	struct Marker;
+
+	/// Spawns a marked entity and increments the counter.
+	#[derive(Default)]
+	struct SpawnAndCount(Option<Entity>);
+
+	impl Operation for SpawnAndCount {
+		fn apply(&mut self, commands: &mut Commands) {
+			self.0 = Some(commands.spawn(Marker).id());
+			commands.queue(|world: &mut World| world.resource_mut::<Counter>().0 += 1);
+		}
+
+		fn undo(&self, commands: &mut Commands) {
+			if let Some(entity) = self.0 {
+				commands.entity(entity).despawn();
+			}
+			commands.queue(|world: &mut World| world.resource_mut::<Counter>().0 -= 1);
+		}
+	}
+
+	struct UnnamedOperation;
+
+	impl Operation for UnnamedOperation {
+		fn apply(&mut self, _commands: &mut Commands) {}
+
+		fn undo(&self, _commands: &mut Commands) {}
+	}
+
+	#[rstest]
+	fn details_defaults_to_type_name() {
+		assert_eq!(UnnamedOperation.details().name, "UnnamedOperation");
+	}
+
+	/// Adds to [`Counter`], merging with consecutive `MergingAdd`s.
+	struct MergingAdd(i32);
+
+	impl Operation for MergingAdd {
+		fn apply(&mut self, commands: &mut Commands) {
+			AddToCounter(self.0).apply(commands);
+		}
+
+		fn undo(&self, commands: &mut Commands) {
+			AddToCounter(self.0).undo(commands);
+		}
+
+		fn merge_into(self: Box<Self>, next: Box<dyn Operation>) -> CoalesceResult {
+			match next.downcast::<Self>() {
+				Ok(next) => Ok(Box::new(Self(self.0 + next.0))),
+				Err(next) => Err((self, next)),
+			}
+		}
+	}
+
+	#[rstest]
+	fn then_undoes_in_reverse() {
+		/// Records its name in [`Order`] when applied or undone.
+		struct Named(&'static str);
+
+		impl Operation for Named {
+			fn apply(&mut self, commands: &mut Commands) {
+				let name = self.0;
+				commands.queue(move |world: &mut World| {
+					world.resource_mut::<Order>().0.push(name.to_owned());
+				});
+			}
+
+			fn undo(&self, commands: &mut Commands) {
+				let name = self.0;
+				commands.queue(move |world: &mut World| {
+					world.resource_mut::<Order>().0.push(name.to_uppercase());
+				});
+			}
+		}
+
+		#[derive(Default, Resource)]
+		struct Order(Vec<String>);
+
+		let mut group = Named("move").then(Named("rename")).then(Named("recolor"));
+		assert_eq!(group.details().name, "Named");
+
+		let mut world = World::new();
+		world.init_resource::<Order>();
+		let mut queue = CommandQueue::default();
+		Operation::apply(&mut group, &mut Commands::new(&mut queue, &world));
+		group.undo(&mut Commands::new(&mut queue, &world));
+		queue.apply(&mut world);
+		assert_eq!(
+			world.resource::<Order>().0,
+			["move", "rename", "recolor", "RECOLOR", "RENAME", "MOVE"]
+		);
+	}
+
+	#[rstest]
+	fn merge_into_combines_or_gives_back_both() {
+		let previous: Box<dyn Operation> = Box::new(MergingAdd(1));
+		let Ok(merged) = previous.merge_into(Box::new(MergingAdd(2))) else {
+			panic!("operations of the same type should merge");
+		};
+		assert_eq!(merged.downcast_ref::<MergingAdd>().unwrap().0, 3);
+
+		let Err((merged, unnamed)) = merged.merge_into(Box::new(UnnamedOperation)) else {
+			panic!("operations of different types should not merge");
+		};
+		assert!(details_eq(&*merged, &MergingAdd(0)));
+		assert!(!details_eq(&*merged, &*unnamed));
+		assert_eq!(merged.downcast_ref::<MergingAdd>().unwrap().0, 3);
+		assert!(unnamed.is::<UnnamedOperation>());
+
+		let Err((unnamed, _)) = unnamed.merge_into(merged) else {
+			panic!("operations should not merge by default");
+		};
+		assert!(unnamed.downcast::<MergingAdd>().is_err());
+	}
+
+	#[rstest]
+	fn preview_apply_matches_direct_apply() {
+		let mut direct = World::new();
+		direct.init_resource::<Counter>();
+		let mut direct_op = SpawnAndCount::default();
+		direct_op.apply(&mut direct.commands());
+		direct.flush();
+
+		let mut previewed = World::new();
+		previewed.init_resource::<Counter>();
+		let mut previewed_op = SpawnAndCount::default();
+		let mut queue = previewed_op.preview_apply(&previewed);
+		assert_eq!(previewed.resource::<Counter>().0, 0);
+		queue.apply(&mut previewed);
+
+		for (world, op) in [(&mut direct, &direct_op), (&mut previewed, &previewed_op)] {
+			assert_eq!(world.resource::<Counter>().0, 1);
+			let mut marked = world.query_filtered::<Entity, With<Marker>>();
+			assert_eq!(marked.iter(world).collect::<Vec<_>>(), vec![op.0.unwrap()]);
+		}
+	}
+
+	/// Tests for [`verify_roundtrip()`], which needs scenes and a debug build.
+	#[cfg(all(feature = "scene", debug_assertions))]
+	mod roundtrip {
+		use super::*;
+		use bevy_ecs::reflect::{AppTypeRegistry, ReflectComponent};
+		use bevy_reflect::Reflect;
+
+		#[derive(Clone, Copy, Debug, PartialEq, Component, Reflect)]
+		#[reflect(Component)]
+		struct Health(u32);
+
+		/// Sets an entity's [`Health`] from one value to another. When undone, it is set back to
+		/// `undo_to`, which is only correct if that's the value it was set from.
+		#[derive(Clone)]
+		struct SetHealth {
+			entity: Entity,
+			to: u32,
+			undo_to: u32,
+		}
+
+		impl Operation for SetHealth {
+			fn apply(&mut self, commands: &mut Commands) {
+				commands.entity(self.entity).insert(Health(self.to));
+			}
+
+			fn undo(&self, commands: &mut Commands) {
+				commands.entity(self.entity).insert(Health(self.undo_to));
+			}
+		}
+
+		#[rstest]
+		#[case::correct(10, true)]
+		#[case::broken(0, false)]
+		fn verify_roundtrip_checks_undo(#[case] undo_to: u32, #[case] expected: bool) {
+			let mut world = World::new();
+			world.init_resource::<AppTypeRegistry>();
+			world
+				.resource::<AppTypeRegistry>()
+				.write()
+				.register::<Health>();
+			let entity = world.spawn(Health(10)).id();
+			world.spawn(Health(20));
+
+			let operation = SetHealth {
+				entity,
+				to: 5,
+				undo_to,
+			};
+			assert_eq!(verify_roundtrip(&operation, &mut world), expected);
+			assert_eq!(world.get::<Health>(entity), Some(&Health(undo_to)));
+		}
+	}
+}
@@ -0,0 +1,184 @@
+//! A [`Plugin`] for setting up undo/redo functionality in an [`App`].
+
+use bevy_app::{App, Plugin, PostUpdate};
+use bevy_ecs::schedule::{IntoSystemConfigs, IntoSystemSetConfigs, SystemSet};
+use bevy_state::{condition::in_state, state::States};
+
+use std::sync::{Mutex, PoisonError};
+
+use crate::undoredo::{apply_queued_operations, UndoRedo, UndoRedoBuilder};
+
+/// A [`Plugin`] which inserts an [`UndoRedo`] resource, and schedules [`apply_queued_operations`]
+/// to run during [`PostUpdate`].
+///
+/// The systems added by this plugin are placed in [`UndoRedoSystems`]. Any run conditions set on
+/// this plugin are applied to that set, so systems you add to it yourself (such as input handling)
+/// will share them.
+#[expect(
+	clippy::module_name_repetitions,
+	reason = "`UndoRedoPlugin` follows Bevy's naming convention for plugins."
+)]
+#[derive(Default)]
+pub struct UndoRedoPlugin {
+	/// A function which adds run conditions to [`UndoRedoSystems`], if any have been requested.
+	configure_systems: Option<Box<ConfigureSystemsFn>>,
+	/// The builder used to create the inserted [`UndoRedo`], if one was given. This is taken when
+	/// the plugin is built.
+	builder: Mutex<Option<UndoRedoBuilder>>,
+}
+
+impl UndoRedoPlugin {
+	/// Creates a new `UndoRedoPlugin`, whose systems will only run while the app is in `state`.
+	///
+	/// This uses the [`in_state`] run condition, so `S` must be set up on the app (i.e. through
+	/// `App::init_state()`) for the systems to run at all.
+	#[must_use]
+	pub fn run_in_state<S: States>(state: S) -> Self {
+		Self {
+			configure_systems: Some(Box::new(move |app: &mut App| {
+				app.configure_sets(PostUpdate, UndoRedoSystems.run_if(in_state(state.clone())));
+			})),
+			..Self::default()
+		}
+	}
+
+	/// Configures the [`UndoRedo`] inserted by this plugin using `builder`.
+	///
+	/// Without this, the plugin inserts a default `UndoRedo`, unless one was already inserted.
+	#[must_use]
+	pub fn with_builder(self, builder: UndoRedoBuilder) -> Self {
+		Self {
+			builder: Mutex::new(Some(builder)),
+			..self
+		}
+	}
+}
+
+impl Plugin for UndoRedoPlugin {
+	fn build(&self, app: &mut App) {
+		let builder = self
+			.builder
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.take();
+		match builder {
+			Some(builder) => app.insert_resource(builder.build()),
+			None => app.init_resource::<UndoRedo>(),
+		};
+		app.add_systems(PostUpdate, apply_queued_operations.in_set(UndoRedoSystems));
+
+		if let Some(configure_systems) = &self.configure_systems {
+			configure_systems(app);
+		}
+	}
+}
+
+/// A function which configures the systems added by [`UndoRedoPlugin`].
+type ConfigureSystemsFn = dyn Fn(&mut App) + Send + Sync;
+
+/// The [`SystemSet`] containing the systems added by [`UndoRedoPlugin`].
+#[derive(Clone, Debug, Hash, PartialEq, Eq, SystemSet)]
+pub struct UndoRedoSystems;
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::test_fixtures::{AddToCounter, Counter};
+	use bevy_app::Last;
+	use bevy_ecs::{
+		change_detection::DetectChanges,
+		system::{Res, ResMut, Resource},
+		world::Mut,
+	};
+	use bevy_state::{
+		app::{AppExtStates, StatesPlugin},
+		state::NextState,
+	};
+	use core::num::NonZeroUsize;
+	use rstest::rstest;
+
+	#[derive(Clone, Debug, Default, Hash, PartialEq, Eq, States)]
+	enum AppState {
+		#[default]
+		Menu,
+		Editor,
+	}
+
+	#[rstest]
+	fn run_in_state_defers_until_state_entered() {
+		let mut app = App::new();
+		app.add_plugins((StatesPlugin, UndoRedoPlugin::run_in_state(AppState::Editor)));
+		app.init_state::<AppState>();
+		app.init_resource::<Counter>();
+
+		app.world_mut()
+			.resource_mut::<UndoRedo>()
+			.push_to_queue(AddToCounter(1))
+			.unwrap();
+		app.update();
+		assert_eq!(app.world().resource::<Counter>().0, 0);
+
+		app.world_mut()
+			.resource_mut::<NextState<AppState>>()
+			.set(AppState::Editor);
+		app.update();
+		assert_eq!(app.world().resource::<Counter>().0, 1);
+	}
+
+	#[rstest]
+	fn empty_queue_frame_does_not_mark_changed() {
+		#[derive(Default, Resource)]
+		struct ChangeCount(u32);
+
+		let mut app = App::new();
+		app.add_plugins(UndoRedoPlugin::default());
+		app.init_resource::<Counter>();
+		app.init_resource::<ChangeCount>();
+		app.add_systems(
+			Last,
+			|undoredo: Res<UndoRedo>, mut changes: ResMut<ChangeCount>| {
+				if undoredo.is_changed() {
+					changes.0 += 1;
+				}
+			},
+		);
+
+		// The first frame sees the resource as newly added.
+		app.update();
+		assert_eq!(app.world().resource::<ChangeCount>().0, 1);
+
+		app.update();
+		app.update();
+		assert_eq!(app.world().resource::<ChangeCount>().0, 1);
+
+		app.world_mut()
+			.resource_mut::<UndoRedo>()
+			.push_to_queue(AddToCounter(1))
+			.unwrap();
+		app.update();
+		assert_eq!(app.world().resource::<ChangeCount>().0, 2);
+	}
+
+	#[rstest]
+	fn with_builder_configures_inserted_resource() {
+		let mut app = App::new();
+		let builder = UndoRedoBuilder::new().limit(NonZeroUsize::MIN);
+		app.add_plugins(UndoRedoPlugin::default().with_builder(builder));
+		app.init_resource::<Counter>();
+
+		for _ in 0..2 {
+			app.world_mut()
+				.resource_mut::<UndoRedo>()
+				.push_to_queue(AddToCounter(1))
+				.unwrap();
+			app.update();
+		}
+
+		let world = app.world_mut();
+		world.resource_scope(|world, mut undoredo: Mut<UndoRedo>| {
+			undoredo.undo_world(world).unwrap();
+			assert!(!undoredo.can_undo());
+		});
+		assert_eq!(world.resource::<Counter>().0, 1);
+	}
+}
@@ -0,0 +1,163 @@
+//! A registry of operation types, allowing editor tooling to list and create operations
+//! generically.
+//!
+//! [`OperationRegistry`] maps the name of each registered operation type to a function which
+//! creates a new instance of it, along with [`OperationMetadata`] for displaying it. This is useful
+//! for data-driven interfaces such as a command palette, which need to offer every available
+//! operation without knowing their types.
+
+use std::collections::{btree_map::Entry, BTreeMap};
+
+use bevy_ecs::system::Resource;
+use disqualified::ShortName;
+
+use crate::operation::Operation;
+
+/// A function which creates a new operation.
+type Factory = dyn Fn() -> Box<dyn Operation> + Send + Sync;
+
+/// Information used to display a registered operation type, such as in a command palette.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct OperationMetadata {
+	/// The name to display for the operation type.
+	pub display_name: String,
+	/// An app-defined identifier for the icon to display alongside the operation type, if any.
+	pub icon: Option<String>,
+	/// The category to list the operation type under, if any.
+	pub category: Option<String>,
+}
+
+/// A [`Resource`] mapping operation type names to functions which create those operations, along
+/// with [`OperationMetadata`] for each.
+///
+/// Types are registered under their name with any module paths removed (i.e.
+/// `my_game::ops::MoveObject` is registered as `MoveObject`), the same as the default
+/// [`Operation::details()`]. Registering a type with the same name as another replaces it.
+#[expect(
+	clippy::module_name_repetitions,
+	reason = "`OperationRegistry` is clearer than `Registry` when imported elsewhere."
+)]
+#[derive(Default, Resource)]
+pub struct OperationRegistry {
+	/// The registered operation types, keyed by name.
+	registrations: BTreeMap<String, Registration>,
+}
+
+impl OperationRegistry {
+	/// Creates a new, empty `OperationRegistry`.
+	#[must_use]
+	pub const fn new() -> Self {
+		Self {
+			registrations: BTreeMap::new(),
+		}
+	}
+
+	/// Registers the operation type `O`, which `factory` creates new instances of.
+	///
+	/// Returns the type's metadata, so that it can be filled in. Its display name defaults to the
+	/// type's name.
+	pub fn register<O: Operation>(
+		&mut self,
+		factory: impl Fn() -> O + Send + Sync + 'static,
+	) -> &mut OperationMetadata {
+		let name = ShortName::of::<O>().to_string();
+		let registration = Registration {
+			metadata: OperationMetadata {
+				display_name: name.clone(),
+				..OperationMetadata::default()
+			},
+			factory: Box::new(move || Box::new(factory())),
+		};
+		let registration = match self.registrations.entry(name) {
+			Entry::Vacant(entry) => entry.insert(registration),
+			Entry::Occupied(mut entry) => {
+				entry.insert(registration);
+				entry.into_mut()
+			}
+		};
+		&mut registration.metadata
+	}
+
+	/// Creates a new instance of the operation type registered as `name`, if any.
+	#[must_use]
+	pub fn create(&self, name: &str) -> Option<Box<dyn Operation>> {
+		let registration = self.registrations.get(name)?;
+		Some((registration.factory)())
+	}
+
+	/// Returns the metadata of the operation type registered as `name`, if any.
+	#[must_use]
+	pub fn metadata(&self, name: &str) -> Option<&OperationMetadata> {
+		Some(&self.registrations.get(name)?.metadata)
+	}
+
+	/// Returns an iterator over the name and metadata of each registered operation type, sorted
+	/// by name.
+	pub fn iter(&self) -> impl Iterator<Item = (&str, &OperationMetadata)> + '_ {
+		self.registrations
+			.iter()
+			.map(|(name, registration)| (name.as_str(), &registration.metadata))
+	}
+}
+
+/// A single operation type registered in an [`OperationRegistry`].
+struct Registration {
+	/// Information used to display the operation type.
+	metadata: OperationMetadata,
+	/// Creates a new instance of the operation type.
+	factory: Box<Factory>,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use bevy_ecs::system::Commands;
+	use rstest::rstest;
+
+	#[derive(Default)]
+	struct ToggleGrid;
+
+	impl Operation for ToggleGrid {
+		fn apply(&mut self, _commands: &mut Commands) {}
+
+		fn undo(&self, _commands: &mut Commands) {}
+	}
+
+	struct Nudge(i32);
+
+	impl Operation for Nudge {
+		fn apply(&mut self, _commands: &mut Commands) {}
+
+		fn undo(&self, _commands: &mut Commands) {}
+	}
+
+	#[rstest]
+	fn registered_types_are_listed_and_created_by_name() {
+		let mut registry = OperationRegistry::new();
+		registry.register(ToggleGrid::default).category = Some("View".to_owned());
+		let nudge = registry.register(|| Nudge(1));
+		nudge.display_name = "Nudge Right".to_owned();
+
+		let listed: Vec<_> = registry
+			.iter()
+			.map(|(name, metadata)| (name, metadata.display_name.as_str()))
+			.collect();
+		assert_eq!(
+			listed,
+			[("Nudge", "Nudge Right"), ("ToggleGrid", "ToggleGrid")]
+		);
+		assert_eq!(
+			registry.metadata("ToggleGrid").unwrap().category.as_deref(),
+			Some("View")
+		);
+
+		let created = registry.create("Nudge").unwrap();
+		assert_eq!(
+			created.downcast_ref::<Nudge>().map(|nudge| nudge.0),
+			Some(1)
+		);
+		assert!(registry.create("ToggleGrid").unwrap().is::<ToggleGrid>());
+		assert!(registry.create("Missing").is_none());
+	}
+}
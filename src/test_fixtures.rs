@@ -0,0 +1,31 @@
+//! Resources and operations shared between this crate's tests.
+
+use bevy_ecs::{
+	system::{Commands, Resource},
+	world::World,
+};
+
+use crate::operation::{Details, Operation};
+
+/// A resource which test operations add to and subtract from.
+#[derive(Default, Resource)]
+pub(crate) struct Counter(pub(crate) i32);
+
+/// Adds the contained value to [`Counter`].
+pub(crate) struct AddToCounter(pub(crate) i32);
+
+impl Operation for AddToCounter {
+	fn details(&self) -> Details {
+		Details::default()
+	}
+
+	fn apply(&mut self, commands: &mut Commands) {
+		let amount = self.0;
+		commands.queue(move |world: &mut World| world.resource_mut::<Counter>().0 += amount);
+	}
+
+	fn undo(&self, commands: &mut Commands) {
+		let amount = self.0;
+		commands.queue(move |world: &mut World| world.resource_mut::<Counter>().0 -= amount);
+	}
+}
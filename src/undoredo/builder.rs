@@ -0,0 +1,112 @@
+//! A builder for configuring an [`UndoRedo`] before it is created.
+
+use core::num::NonZeroUsize;
+
+use super::{QueueOrder, RecordingUndoRedo, UndoRedo};
+use crate::{coalescing::CoalescePolicy, history::HistoryMode};
+
+/// A builder for an [`UndoRedo`], allowing its settings to be configured in one place.
+///
+/// Any setting which is not configured keeps the same default as [`UndoRedo::new()`].
+///
+/// A configured builder can also be given to [`UndoRedoPlugin::with_builder()`], so that the
+/// `UndoRedo` resource inserted by the plugin is configured as well.
+///
+/// [`UndoRedoPlugin::with_builder()`]: crate::plugin::UndoRedoPlugin::with_builder()
+#[derive(Default)]
+pub struct UndoRedoBuilder {
+	/// The maximum number of applied operations to keep.
+	limit: Option<NonZeroUsize>,
+	/// The soft limit on the number of applied operations.
+	warn_threshold: Option<NonZeroUsize>,
+	/// The order in which queued operations are applied.
+	queue_order: QueueOrder,
+	/// Whether applied operations are recorded.
+	history_mode: HistoryMode,
+	/// The policy deciding whether newly-applied operations are merged into the previous one.
+	coalesce_policy: Option<Box<dyn CoalescePolicy>>,
+}
+
+impl UndoRedoBuilder {
+	/// Creates a new `UndoRedoBuilder`, with every setting left at its default.
+	#[must_use]
+	pub const fn new() -> Self {
+		Self {
+			limit: None,
+			warn_threshold: None,
+			queue_order: QueueOrder::Fifo,
+			history_mode: HistoryMode::Recording,
+			coalesce_policy: None,
+		}
+	}
+
+	/// Sets the maximum number of applied operations to keep. Once this is reached, the oldest
+	/// applied operations are dropped to make room for new ones.
+	///
+	/// # See Also
+	/// * [`History::limit`](crate::history::History::limit)
+	#[must_use]
+	pub const fn limit(mut self, limit: NonZeroUsize) -> Self {
+		self.limit = Some(limit);
+		self
+	}
+
+	/// Sets a soft limit on the number of applied operations.
+	///
+	/// # See Also
+	/// * [`UndoRedo::set_warn_threshold()`]
+	#[must_use]
+	pub const fn warn_threshold(mut self, threshold: NonZeroUsize) -> Self {
+		self.warn_threshold = Some(threshold);
+		self
+	}
+
+	/// Sets the order in which queued operations are applied.
+	///
+	/// # See Also
+	/// * [`UndoRedo::set_queue_order()`]
+	#[must_use]
+	pub const fn queue_order(mut self, order: QueueOrder) -> Self {
+		self.queue_order = order;
+		self
+	}
+
+	/// Sets whether applied operations are recorded.
+	///
+	/// # See Also
+	/// * [`UndoRedo::set_history_mode()`]
+	#[must_use]
+	pub const fn history_mode(mut self, mode: HistoryMode) -> Self {
+		self.history_mode = mode;
+		self
+	}
+
+	/// Sets the [`CoalescePolicy`] used to decide whether newly-applied operations are merged into
+	/// the previously-applied operation.
+	///
+	/// # See Also
+	/// * [`UndoRedo::set_coalesce_policy()`]
+	#[must_use]
+	pub fn coalesce_policy<P: CoalescePolicy>(mut self, policy: P) -> Self {
+		self.coalesce_policy = Some(Box::new(policy));
+		self
+	}
+
+	/// Creates an [`UndoRedo`] with the configured settings.
+	#[must_use]
+	pub fn build(self) -> UndoRedo {
+		let mut undoredo = UndoRedo::new();
+		undoredo.history.limit = self.limit;
+		undoredo.history.warn_threshold = self.warn_threshold;
+		undoredo.queue_order = self.queue_order;
+		undoredo.history.mode = self.history_mode;
+		undoredo.coalesce_policy = self.coalesce_policy;
+		undoredo
+	}
+
+	/// Creates a [`RecordingUndoRedo`], wrapping an [`UndoRedo`] with the configured settings.
+	#[must_use]
+	pub fn build_recording(self) -> RecordingUndoRedo {
+		RecordingUndoRedo::new(self.build())
+	}
+}
@@ -0,0 +1,3676 @@
+//! A high-level interface for implementing undo/redo functionality.
+mod builder;
+mod recording;
+mod snapshot;
+
+use core::{
+	any::Any,
+	num::NonZeroUsize,
+	panic::AssertUnwindSafe,
+	sync::atomic::{AtomicU64, Ordering},
+};
+
+use std::{collections::VecDeque, sync::Arc};
+
+use bevy_ecs::{
+	event::{Event, EventWriter, Events},
+	system::{Commands, Res, ResMut, Resource},
+	world::{CommandQueue, World},
+};
+
+use crate::{
+	coalescing::{CoalesceKey, CoalescePolicy},
+	common_operations::OperationGroup,
+	error::Error,
+	history::{History, HistoryMode, Hook, ThresholdHook},
+	operation::{Details, Operation, OperationContext},
+};
+
+pub use self::{
+	builder::UndoRedoBuilder,
+	recording::{HistoryEvent, RecordingUndoRedo},
+	snapshot::{AuditEntry, HistorySnapshot, OperationState, SnapshotEntry},
+};
+
+/// A high-level interface for implementing undo/redo functionality.
+///
+/// If you've inserted an `UndoRedo` as a `Resource` into a world and want to undo/redo an
+/// operation, consider using [`CommandsUndoRedoExt`], which negates the need to supply a `Commands`
+/// yourself.
+///
+/// # Operation States
+/// Operations are given one of three states:
+///
+/// * **Queued** - The operation has been pushed to the `UndoRedo`, and is ready to be applied to
+///   the world.
+/// * **Applied** - The operation's effects have been applied to the world, or will be applied the
+///   next time [`Commands`] are applied. You can undo these operations.
+/// * **Undone** - The operation's effects have been reversed , or will be the next time
+///   [`Commands`] are applied). They can be redone, but all undone operations are lost the next
+///   time an operation is marked as **Committed**.
+///
+/// # Change Detection
+/// Systems can react to changes in an `UndoRedo` resource (such as to refresh a history panel)
+/// using Bevy's change detection. [`apply_queued_operations`] and the commands added by
+/// [`CommandsUndoRedoExt`] avoid marking the resource as changed when they have nothing to do,
+/// such as when the queue is empty, or when there is nothing to undo.
+///
+/// Note that calling any `&mut self` method through a [`ResMut`] marks the resource as changed,
+/// even if that method fails. If this matters, check first through an immutable reference (i.e.
+/// with [`Self::can_undo()`]).
+///
+/// [`CommandsUndoRedoExt`]: crate::extensions::CommandsUndoRedoExt
+// TODO List:
+// * `impl Extend<Box<dyn Operation>> for UndoRedo`
+// * `impl FromIterator<Box<dyn Operation>> for UndoRedo`
+// * `impl IntoIterator for UndoRedo`
+//   * Plus `iter()`, `iter_committed()`, `iter_undone()`, `iter_queued()`
+#[derive(Default, Resource)]
+pub struct UndoRedo {
+	/// The collection which manages the list of applied and undone operations, and acts as a
+	/// pointer into that set of items.
+	history: History<HistoryEntry>,
+	/// A list of operations that have been pushed to this [`UndoRedo`], but have not been applied
+	/// to the World.
+	queued_operations: VecDeque<QueuedOperation>,
+	/// The policy deciding whether newly-applied operations are merged into the previous one. If
+	/// `None`, [`Operation::merge_into()`] decides instead.
+	coalesce_policy: Option<Box<dyn CoalescePolicy>>,
+	/// The key deciding whether newly-applied operations are grouped with the previous one,
+	/// before the coalesce policy is consulted.
+	coalesce_by: CoalesceKey,
+	/// The order in which queued operations are applied.
+	queue_order: QueueOrder,
+	/// Whether an operation which inverts the previously-applied operation cancels it out, rather
+	/// than being recorded.
+	cancel_inverses: bool,
+	/// The operations applied so far by the gesture in progress, if any.
+	gesture: Option<OperationGroup>,
+	/// The point in the history marked by [`Self::set_savepoint()`], if any.
+	savepoint: Option<Savepoint>,
+	/// The current frame number, if operations are being grouped by frame. See
+	/// [`Self::set_current_frame()`].
+	current_frame: Option<u64>,
+	/// The operations committed so far by the [batch](Self::batch()) in progress, if any.
+	batch: Option<OperationGroup>,
+}
+
+impl UndoRedo {
+	/// Creates a new, empty `UndoRedo`.
+	#[must_use]
+	pub const fn new() -> Self {
+		Self {
+			history: History::new(),
+			queued_operations: VecDeque::new(),
+			coalesce_policy: None,
+			coalesce_by: CoalesceKey::None,
+			queue_order: QueueOrder::Fifo,
+			cancel_inverses: false,
+			gesture: None,
+			savepoint: None,
+			current_frame: None,
+			batch: None,
+		}
+	}
+
+	/// Creates a new `UndoRedo`, whose history is made up of the operations in `history`.
+	///
+	/// The operations in `history` are assumed to be in the state that `history` says they're in -
+	/// that is, committed operations are assumed to have been applied, and undone operations are
+	/// assumed to have been undone. This is useful for restoring a previously-saved history.
+	#[must_use]
+	pub fn with_history(history: History<Box<dyn Operation>>) -> Self {
+		let mut history = history.map(HistoryEntry::new);
+
+		// New entries are marked as applied, so mark the undone ones as not applied.
+		let mut offset = 1;
+		while let Some(entry) = history.peek_mut(offset) {
+			entry.applied = false;
+			offset += 1;
+		}
+
+		Self {
+			history,
+			..Self::new()
+		}
+	}
+}
+
+/// Configuration.
+impl UndoRedo {
+	/// Sets the [`CoalescePolicy`] used to decide whether newly-applied operations are merged into
+	/// the previously-applied operation.
+	///
+	/// By default, no policy is set, and [`Operation::merge_into()`] decides whether operations are
+	/// merged instead.
+	pub fn set_coalesce_policy<P: CoalescePolicy>(&mut self, policy: P) {
+		self.coalesce_policy = Some(Box::new(policy));
+	}
+
+	/// Removes any [`CoalescePolicy`] set on this `UndoRedo`, such that operations are never
+	/// merged.
+	pub fn clear_coalesce_policy(&mut self) {
+		self.coalesce_policy = None;
+	}
+
+	/// Sets the [`CoalesceKey`] used to fold consecutive operations together without a
+	/// [`CoalescePolicy`].
+	///
+	/// When a newly-applied operation matches the previously-applied one by `key`, both are
+	/// grouped together into an [`OperationGroup`], named after the earlier operation, and so are
+	/// undone as one step. Otherwise, the coalesce policy or [`Operation::merge_into()`] decides
+	/// as usual. By default, this is [`CoalesceKey::None`].
+	pub const fn set_coalesce_by(&mut self, key: CoalesceKey) {
+		self.coalesce_by = key;
+	}
+
+	/// Returns the [`CoalesceKey`] used to fold consecutive operations together.
+	#[must_use]
+	pub const fn coalesce_by(&self) -> CoalesceKey {
+		self.coalesce_by
+	}
+
+	/// Sets the order in which queued operations are applied, such as by [`Self::apply_queue()`].
+	///
+	/// By default, this is [`QueueOrder::Fifo`].
+	pub const fn set_queue_order(&mut self, order: QueueOrder) {
+		self.queue_order = order;
+	}
+
+	/// Returns the order in which queued operations are applied.
+	#[must_use]
+	pub const fn queue_order(&self) -> QueueOrder {
+		self.queue_order
+	}
+
+	/// Sets whether a newly-applied operation which [inverts] the previously-applied operation
+	/// cancels it out. If so, the previous operation is removed from the history, and the new one
+	/// is not recorded, as together they have no effect.
+	///
+	/// This is checked before the operations are given a chance to be merged. By default, this is
+	/// disabled.
+	///
+	/// [inverts]: Operation::inverts()
+	pub const fn set_cancel_inverses(&mut self, enabled: bool) {
+		self.cancel_inverses = enabled;
+	}
+
+	/// Sets the current frame number, such as from Bevy's `FrameCount` resource, enabling
+	/// operations to be grouped by frame. Setting `None` disables this, which is the default.
+	///
+	/// While enabled, an operation applied in the same frame as the previously-applied operation
+	/// is grouped together with it into an [`OperationGroup`], so that everything applied in a
+	/// frame is undone as one step. This takes the place of merging them through the coalesce
+	/// policy or [`Operation::merge_into()`]. This should be called once per frame, before any
+	/// operations are applied - for example, from a system in the `First` schedule.
+	pub const fn set_current_frame(&mut self, frame: Option<u64>) {
+		self.current_frame = frame;
+	}
+
+	/// Returns the current frame number set through [`Self::set_current_frame()`], if operations
+	/// are being grouped by frame.
+	#[must_use]
+	pub const fn current_frame(&self) -> Option<u64> {
+		self.current_frame
+	}
+
+	/// Registers a callback to be given the [`Details`] of each operation evicted from the history,
+	/// such as when the history limit is reached, or when undone operations are discarded because
+	/// a new operation was applied. This is useful for keeping an audit log of dropped operations.
+	///
+	/// This replaces any callback previously registered through this method.
+	///
+	/// # See Also
+	/// * [`History::set_on_evict()`]
+	pub fn set_on_evict(&mut self, mut hook: Hook<Details>) {
+		self.history
+			.set_on_evict(Box::new(move |entry: &HistoryEntry| {
+				hook(&entry.operation.details());
+			}));
+	}
+
+	/// Removes the callback registered through [`Self::set_on_evict()`], if any.
+	pub fn clear_on_evict(&mut self) {
+		self.history.clear_on_evict();
+	}
+
+	/// Sets a soft limit on the number of applied operations. Unlike the history limit, no
+	/// operations are dropped when this is reached; instead, the callback registered through
+	/// [`Self::set_on_warn_threshold()`] is called.
+	///
+	/// # See Also
+	/// * [`History::warn_threshold`]
+	pub const fn set_warn_threshold(&mut self, threshold: Option<NonZeroUsize>) {
+		self.history.warn_threshold = threshold;
+	}
+
+	/// Sets whether applied operations are recorded. If [`HistoryMode::Disabled`], operations are
+	/// still applied, but are dropped rather than being pushed to the list of applied operations,
+	/// and so can't be undone.
+	///
+	/// By default, this is [`HistoryMode::Recording`].
+	///
+	/// # See Also
+	/// * [`History::mode`]
+	pub const fn set_history_mode(&mut self, mode: HistoryMode) {
+		self.history.mode = mode;
+	}
+
+	/// Returns whether applied operations are recorded.
+	#[must_use]
+	pub const fn history_mode(&self) -> HistoryMode {
+		self.history.mode
+	}
+
+	/// Registers a callback to be given the number of applied operations when that number reaches
+	/// the threshold set through [`Self::set_warn_threshold()`]. This is useful for suggesting that
+	/// the user save or clear their history.
+	///
+	/// This replaces any callback previously registered through this method.
+	///
+	/// # See Also
+	/// * [`History::set_on_warn_threshold()`]
+	pub fn set_on_warn_threshold(&mut self, hook: ThresholdHook) {
+		self.history.set_on_warn_threshold(hook);
+	}
+
+	/// Removes the callback registered through [`Self::set_on_warn_threshold()`], if any.
+	pub fn clear_on_warn_threshold(&mut self) {
+		self.history.clear_on_warn_threshold();
+	}
+}
+
+impl UndoRedo {
+	/// Returns this `UndoRedo` to a fresh state, as if it had just been created with its current
+	/// configuration. This is useful for a "New Document" action.
+	///
+	/// All applied, undone, and queued operations are dropped, along with any gesture in progress
+	/// and the savepoint. Configuration - such as the history limit, history mode, coalesce policy,
+	/// queue order, and any registered callbacks - is kept.
+	///
+	/// # See Also
+	/// * [`Self::clear()`] - Clears the stored operations, but leaves any gesture in progress.
+	pub fn reset(&mut self) {
+		self.clear();
+		self.gesture = None;
+	}
+
+	/// Clears all stored operations, including those that are still queued.
+	pub fn clear(&mut self) {
+		self.history.clear();
+		self.queued_operations.clear();
+		self.savepoint = None;
+	}
+
+	/// Clears the list of queued operations.
+	pub fn clear_queue(&mut self) {
+		self.queued_operations.clear();
+	}
+
+	/// Pushes an operation into the list of queued operations. Queued operations are those that are
+	/// ready to be applied later.
+	///
+	/// After pushing one or more operations, call [`Self::apply_queue()`] to apply the operation(s)
+	/// to the [`World`].
+	///
+	/// # Errors
+	/// * [`Error::InvalidOperation`] - [`Operation::validate()`] failed. The operation is not
+	///   queued.
+	///
+	/// # See Also
+	/// * [`Self::push_and_apply()`] - Pushes an operation, skipping the queue such that it will be
+	///   applied ASAP.
+	///
+	/// [`World`]: bevy_ecs::world::World
+	pub fn push_to_queue<O: Operation>(&mut self, operation: O) -> Result<(), Error> {
+		self.push_to_queue_from(operation, OperationSource::User)
+	}
+
+	/// Pushes an operation into the list of queued operations, as with [`Self::push_to_queue()`],
+	/// recording that it came from `source`.
+	///
+	/// The source is kept alongside the operation once it is applied, and can be read back through
+	/// [`Self::source_of()`].
+	///
+	/// # Errors
+	/// * [`Error::InvalidOperation`] - [`Operation::validate()`] failed. The operation is not
+	///   queued.
+	pub fn push_to_queue_from<O: Operation>(
+		&mut self,
+		operation: O,
+		source: OperationSource,
+	) -> Result<(), Error> {
+		self::validate(&operation)?;
+		self.queued_operations.push_back(QueuedOperation {
+			operation: Box::new(operation),
+			source,
+		});
+		Ok(())
+	}
+
+	/// Queues up the commands needed to apply all queued operations, and moves those queued
+	/// operations to the list of applied operations.
+	///
+	/// The queued operations share a single [`OperationContext`], so operations later in the queue
+	/// can make use of data recorded by earlier ones.
+	///
+	/// Additionally, any operations which have been undone, but not subsequently redone, will be
+	/// lost when calling this.
+	///
+	/// Consecutive queued operations with the same [locality key] are recorded together as one
+	/// [`OperationGroup`], named after the first operation in the run, and so are undone together.
+	/// For example, a brush stroke across a tilemap can be undone one chunk at a time, rather than
+	/// one tile at a time.
+	///
+	/// # Errors
+	/// * [`Error::NoQueuedOperations`] - There are no queued operations available to apply.
+	/// * Any error returned by [`Operation::apply_in_context()`]. The failing operation is dropped
+	///   without being added to the list of applied operations, and any operations queued after it
+	///   are left in the queue.
+	///
+	/// # See Also
+	/// * [`Self::apply_queue_with()`] - Applies all queued operations, reporting failures through a
+	///   callback rather than stopping at the first one.
+	/// * [`Self::apply_queue_limited()`] - Applies only some of the queued operations.
+	///
+	/// [locality key]: Operation::locality_key()
+	// TODO: This should probably be called by a built-in system.
+	pub fn apply_queue(&mut self, commands: &mut Commands) -> Result<(), Error> {
+		self.apply_queue_limited(usize::MAX, commands).map(|_| ())
+	}
+
+	/// Queues up the commands needed to apply up to `max` queued operations, and moves those
+	/// operations to the list of applied operations. Returns the number of operations applied.
+	///
+	/// Any operations past the first `max` are left in the queue. This allows a large queue to be
+	/// applied gradually, such as over several frames. Otherwise, this behaves the same as
+	/// [`Self::apply_queue()`].
+	///
+	/// # Errors
+	/// See [`Self::apply_queue()`].
+	pub fn apply_queue_limited(
+		&mut self,
+		max: usize,
+		commands: &mut Commands,
+	) -> Result<usize, Error> {
+		// If there are no queued operations available, we have no work to do. Let the caller know.
+		if self.queued_operations.is_empty() {
+			return Err(Error::NoQueuedOperations);
+		}
+
+		let mut context = OperationContext::new();
+		let mut applied_count = 0;
+		let mut run: Option<LocalityRun> = None;
+
+		// NOTE: Could this be replaced with something calling `History`'s `Extend` impl?
+		let result = loop {
+			if applied_count >= max {
+				break Ok(());
+			}
+			let Some(QueuedOperation {
+				mut operation,
+				source,
+			}) = self.pop_queued()
+			else {
+				break Ok(());
+			};
+
+			// A change in locality key or source ends the current run, if any.
+			let key = operation.locality_key();
+			if run
+				.as_ref()
+				.is_some_and(|run| Some(run.key) != key || run.source != source)
+			{
+				self.commit_run(run.take());
+			}
+
+			let result = match key {
+				Some(key) => operation
+					.apply_in_context(commands, &mut context)
+					.map(|()| {
+						let run = run.get_or_insert_with(|| LocalityRun {
+							key,
+							source,
+							operations: Vec::new(),
+						});
+						run.operations.push(operation);
+					}),
+				None => self.apply_and_commit(operation, source, commands, &mut context),
+			};
+			if result.is_err() {
+				break result;
+			}
+			applied_count += 1;
+		};
+
+		// Even if an operation failed, those in the run before it have been applied.
+		self.commit_run(run);
+
+		result.map(|()| applied_count)
+	}
+
+	/// Queues up the commands needed to apply all queued operations, and moves those queued
+	/// operations to the list of applied operations, calling `on_error` for any operation that
+	/// fails to apply.
+	///
+	/// Unlike [`Self::apply_queue()`], a failing operation does not stop the rest of the queue from
+	/// being applied. `on_error` is given the failing operation's [`Details`] and the error it
+	/// returned, after which the failing operation is dropped.
+	///
+	/// # Errors
+	/// * [`Error::NoQueuedOperations`] - There are no queued operations available to apply.
+	pub fn apply_queue_with(
+		&mut self,
+		commands: &mut Commands,
+		mut on_error: impl FnMut(&Details, Error),
+	) -> Result<(), Error> {
+		if self.queued_operations.is_empty() {
+			return Err(Error::NoQueuedOperations);
+		}
+
+		let mut context = OperationContext::new();
+
+		while let Some(QueuedOperation { operation, source }) = self.pop_queued() {
+			let details = operation.details();
+			if let Err(error) = self.apply_and_commit(operation, source, commands, &mut context) {
+				on_error(&details, error);
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Queues up the commands needed to apply all queued operations, as with
+	/// [`Self::apply_queue()`], and sets `discarded` to the number of undone operations which were
+	/// lost as a result.
+	///
+	/// This is useful for warning the user that their redo history has been lost. `discarded` is
+	/// set even if an error is returned, as operations applied before the failing one may still
+	/// have caused undone operations to be lost.
+	///
+	/// # Errors
+	/// See [`Self::apply_queue()`].
+	pub fn apply_queue_counting_discarded(
+		&mut self,
+		commands: &mut Commands,
+		discarded: &mut usize,
+	) -> Result<(), Error> {
+		let undone_before = self.history.iter_undone().len();
+		let result = self.apply_queue(commands);
+		*discarded = undone_before - self.history.iter_undone().len();
+		result
+	}
+
+	/// Queues up the commands needed to apply all queued operations, then moves those queued
+	/// operations into `group`, which is pushed to the list of applied operations as a single
+	/// operation.
+	///
+	/// This is the same as [`Self::apply_queue()`], except that undoing will undo every operation
+	/// applied by this call at once.
+	///
+	/// # Errors
+	/// * [`Error::NoQueuedOperations`] - There are no queued operations available to apply.
+	/// * Any error returned by [`Operation::apply_in_context()`]. The failing operation is dropped,
+	///   and any operations queued after it are left in the queue. Operations applied before it
+	///   are still pushed to the list of applied operations, as part of `group`.
+	pub fn apply_queue_into_group(
+		&mut self,
+		mut group: OperationGroup,
+		commands: &mut Commands,
+	) -> Result<(), Error> {
+		if self.queued_operations.is_empty() {
+			return Err(Error::NoQueuedOperations);
+		}
+
+		let mut context = OperationContext::new();
+		let mut result = Ok(());
+		let mut group_source = None;
+
+		while let Some(QueuedOperation {
+			mut operation,
+			source,
+		}) = self.pop_queued()
+		{
+			result = operation.apply_in_context(commands, &mut context);
+			if result.is_err() {
+				break;
+			}
+			group_source.get_or_insert(source);
+			group.push_boxed(operation);
+		}
+
+		// Even if an operation failed, those before it have been applied, and so must be recorded.
+		// The group is attributed to the source of the first queued operation it holds.
+		if !group.is_empty() {
+			let source = group_source.unwrap_or_default();
+			self.commit_from(Box::new(group), source);
+		}
+
+		result
+	}
+
+	/// Queues up the commands needed to apply `operation`, then pushes `operation` to the list of
+	/// applied operations.
+	///
+	/// The list of queued operations is untouched when calling this. However, undone operations
+	/// which have not been subsequently redone *will* be lost, as with [`Self::apply_queue()`].
+	///
+	/// # Errors
+	/// * [`Error::InvalidOperation`] - [`Operation::validate()`] failed. The operation is not
+	///   applied.
+	/// * Any error returned by [`Operation::apply_in_context()`]. In this case, `operation` is
+	///   dropped without being added to the list of applied operations.
+	///
+	/// # See Also
+	/// * [`Self::push_to_queue()`] - Pushes items to a queue, to be applied later all at once.
+	pub fn push_and_apply<O: Operation>(
+		&mut self,
+		operation: O,
+		commands: &mut Commands,
+	) -> Result<(), Error> {
+		self::validate(&operation)?;
+		self.apply_and_commit(
+			Box::new(operation),
+			OperationSource::User,
+			commands,
+			&mut OperationContext::new(),
+		)
+	}
+
+	/// Queues up the commands needed to apply `operation`, then pushes it to the list of applied
+	/// operations as a new entry, bypassing the coalesce policy and [`Operation::merge_into()`].
+	///
+	/// This is useful for operations which must be their own undo step, such as a discrete
+	/// "commit" action in the middle of a run of mergeable edits. Later operations are not merged
+	/// into `operation` either, nor do they cancel it out if [inverse cancellation] is enabled.
+	///
+	/// # Errors
+	/// * [`Error::InvalidOperation`] - [`Operation::validate()`] failed. The operation is not
+	///   applied.
+	/// * Any error returned by [`Operation::apply_in_context()`]. In this case, `operation` is
+	///   dropped without being added to the list of applied operations.
+	///
+	/// [inverse cancellation]: Self::set_cancel_inverses()
+	pub fn push_no_coalesce<O: Operation>(
+		&mut self,
+		mut operation: O,
+		commands: &mut Commands,
+	) -> Result<(), Error> {
+		self::validate(&operation)?;
+		operation.apply_in_context(commands, &mut OperationContext::new())?;
+		self.commit_with(Box::new(operation), false, OperationSource::User);
+		Ok(())
+	}
+
+	/// Starts a gesture, such as a press-drag-release, named `name`.
+	///
+	/// Operations added through [`Self::update_gesture()`] are applied immediately, so the World
+	/// reflects the gesture as it happens. However, they are only recorded once
+	/// [`Self::end_gesture()`] is called, at which point they are recorded together as one
+	/// [`OperationGroup`], and so are undone together.
+	///
+	/// If a gesture is already in progress, it is ended first.
+	pub fn begin_gesture(&mut self, name: impl Into<String>) {
+		let _ = self.end_gesture();
+		self.gesture = Some(OperationGroup::new(Details { name: name.into() }));
+	}
+
+	/// Queues up the commands needed to apply `operation`, then adds it to the gesture in
+	/// progress.
+	///
+	/// # Errors
+	/// * [`Error::NoActiveGesture`] - No gesture is in progress. The operation is not applied.
+	/// * [`Error::InvalidOperation`] - [`Operation::validate()`] failed. The operation is not
+	///   applied.
+	/// * Any error returned by [`Operation::apply_in_context()`]. In this case, `operation` is
+	///   dropped without being added to the gesture, but the gesture remains in progress.
+	pub fn update_gesture<O: Operation>(
+		&mut self,
+		mut operation: O,
+		commands: &mut Commands,
+	) -> Result<(), Error> {
+		let group = self.gesture.as_mut().ok_or(Error::NoActiveGesture)?;
+		self::validate(&operation)?;
+		operation.apply_in_context(commands, &mut OperationContext::new())?;
+		group.push(operation);
+		Ok(())
+	}
+
+	/// Ends the gesture in progress, pushing the operations it applied to the list of applied
+	/// operations as one undo step.
+	///
+	/// If no operations were added to the gesture, nothing is recorded. Otherwise, as with
+	/// [`Self::apply_queue()`], undone operations which have not been subsequently redone will be
+	/// lost.
+	///
+	/// # Errors
+	/// * [`Error::NoActiveGesture`] - No gesture is in progress.
+	pub fn end_gesture(&mut self) -> Result<(), Error> {
+		let group = self.gesture.take().ok_or(Error::NoActiveGesture)?;
+		if !group.is_empty() {
+			self.commit(Box::new(group));
+		}
+		Ok(())
+	}
+
+	/// Returns `true` if a gesture is in progress.
+	#[must_use]
+	pub const fn is_gesture_active(&self) -> bool {
+		self.gesture.is_some()
+	}
+
+	/// Calls `f`, recording every operation it commits as one [`OperationGroup`] named `name`, so
+	/// that they are undone together.
+	///
+	/// Unlike a gesture, operations may be committed through any means within `f` - including
+	/// [`Self::push_and_apply()`] and [`Self::end_gesture()`]. They are not merged with, or
+	/// cancelled out by, each other or the previously-applied operation. If no operations were
+	/// committed, nothing is recorded.
+	///
+	/// The group is recorded when `f` returns, or if it panics, before the panic continues. If a
+	/// batch is already in progress, the operations committed by `f` join that batch instead.
+	pub fn batch<R>(&mut self, name: impl Into<String>, f: impl FnOnce(&mut Self) -> R) -> R {
+		if self.batch.is_some() {
+			return f(self);
+		}
+
+		self.batch = Some(OperationGroup::new(Details { name: name.into() }));
+		let result = std::panic::catch_unwind(AssertUnwindSafe(|| f(self)));
+
+		if let Some(group) = self.batch.take().filter(|group| !group.is_empty()) {
+			self.commit_with(Box::new(group), false, OperationSource::User);
+		}
+		result.unwrap_or_else(|payload| std::panic::resume_unwind(payload))
+	}
+
+	/// Pushes `operation` to the list of applied operations without applying it, such that it can
+	/// be undone.
+	///
+	/// This is useful for making changes undoable after they've been made through other means.
+	/// The caller must ensure that `operation`'s effects are already present in the World - and
+	/// that `operation` holds whatever data it needs to undo them - as undoing it will assume so.
+	/// As with [`Self::push_and_apply()`], undone operations which have not been subsequently
+	/// redone will be lost.
+	///
+	/// # Errors
+	/// * [`Error::InvalidOperation`] - [`Operation::validate()`] failed. The operation is not
+	///   pushed.
+	pub fn register_applied<O: Operation>(&mut self, operation: O) -> Result<(), Error> {
+		self::validate(&operation)?;
+		self.commit(Box::new(operation));
+		Ok(())
+	}
+
+	/// Applies the last undone operation, if any.
+	///
+	/// # Errors
+	/// * [`Error::NoApplicableHistory`] - No operations have been undone since the last time (if any)
+	///   queued operations were applied.
+	/// * Any error returned by [`Operation::apply_in_context()`]. In this case, the operation
+	///   remains undone.
+	///
+	/// # See Also
+	/// * [`CommandsUndoRedoExt::redo()`] - Queues up a call to this method on the world's
+	///   `UndoRedo` resource.
+	///
+	/// [`CommandsUndoRedoExt::redo()`]: crate::extensions::CommandsUndoRedoExt::redo()
+	pub fn redo(&mut self, commands: &mut Commands) -> Result<(), Error> {
+		// The operation is only moved back to the applied list once it has been reapplied, so
+		// that if reapplying fails, it stays undone and no applied operations are dropped to
+		// make space for it.
+		self.history.try_redo(|entry| entry.reapply(commands))
+	}
+
+	/// Undoes the last applied operation, if any.
+	///
+	/// # Errors
+	/// * [`Error::NoApplicableHistory`] - There are no operations available to undo.
+	/// * [`Error::ConfirmationRequired`] - The operation's [`Operation::confirm_undo()`] returned
+	///   `false`. The operation is not undone; use [`Self::undo_confirmed()`] once the user has
+	///   confirmed the undo.
+	///
+	/// # See Also
+	/// * [`CommandsUndoRedoExt::redo()`] - Queues up a call to this method on the world's
+	///   `UndoRedo` resource.
+	///
+	/// [`CommandsUndoRedoExt::redo()`]: crate::extensions::CommandsUndoRedoExt::redo()
+	pub fn undo(&mut self, commands: &mut Commands) -> Result<(), Error> {
+		let entry = self.history.peek(-1).ok_or(Error::NoApplicableHistory)?;
+		if !entry.operation.confirm_undo() {
+			return Err(Error::ConfirmationRequired);
+		}
+
+		self.undo_confirmed(commands)
+	}
+
+	/// Undoes the last applied operation, if any, as with [`Self::undo()`], and records why it was
+	/// undone - for example, whether the user asked for it, or an automatic rollback happened.
+	///
+	/// Once the queued commands are applied, an [`UndoPerformed`] event carrying `reason` is sent,
+	/// if the `Events<UndoPerformed>` resource exists. This helps diagnose unexpected reverts, such
+	/// as by logging these events.
+	///
+	/// # Errors
+	/// See [`Self::undo()`]. No event is sent if the undo fails.
+	pub fn undo_with_reason(
+		&mut self,
+		reason: impl Into<String>,
+		commands: &mut Commands,
+	) -> Result<(), Error> {
+		let details = self.history.peek(-1).map(|entry| entry.operation.details());
+		self.undo(commands)?;
+
+		let event = UndoPerformed {
+			details: details.unwrap_or_default(),
+			reason: reason.into(),
+		};
+		commands.queue(move |world: &mut World| {
+			if let Some(mut events) = world.get_resource_mut::<Events<UndoPerformed>>() {
+				events.send(event);
+			}
+		});
+		Ok(())
+	}
+
+	/// Undoes the last applied operation, if any, as with [`Self::undo()`], but describes what
+	/// happened through an [`UndoOutcome`] rather than an error.
+	///
+	/// This is useful when exposing undo to a scripting language, where a structured outcome is
+	/// easier to handle than an error.
+	pub fn try_undo(&mut self, commands: &mut Commands) -> UndoOutcome {
+		match self.undo(commands) {
+			Ok(()) => UndoOutcome::Performed,
+			Err(Error::ConfirmationRequired) => UndoOutcome::ConfirmationRequired,
+			Err(_) => UndoOutcome::NothingToUndo,
+		}
+	}
+
+	/// Undoes the last applied operation, if any, even if its [`Operation::confirm_undo()`]
+	/// returned `false`.
+	///
+	/// This is intended to be called once the user has confirmed an undo which [`Self::undo()`]
+	/// refused.
+	///
+	/// # Errors
+	/// * [`Error::NoApplicableHistory`] - There are no operations available to undo.
+	pub fn undo_confirmed(&mut self, commands: &mut Commands) -> Result<(), Error> {
+		let item = self.history.undo()?;
+
+		// Submit all the commands needed to undo...
+		item.undo(commands);
+
+		Ok(())
+	}
+
+	/// Undoes the most recent logical action.
+	///
+	/// Usually, this is the same as [`Self::undo()`]. However, if the last applied operation has a
+	/// [transaction ID], then every consecutive applied operation with that same ID is undone as
+	/// well. This allows several operations, stored as separate history entries, to be undone
+	/// together as one user action.
+	///
+	/// # Errors
+	/// * [`Error::NoApplicableHistory`] - There are no operations available to undo.
+	/// * [`Error::ConfirmationRequired`] - One of the operations to be undone must be [confirmed]
+	///   before being undone. Undoing stops at that operation, leaving any after it undone.
+	///
+	/// [transaction ID]: Operation::transaction_id()
+	/// [confirmed]: Operation::confirm_undo()
+	pub fn undo_last_action(&mut self, commands: &mut Commands) -> Result<(), Error> {
+		let transaction_id = self
+			.history
+			.peek(-1)
+			.ok_or(Error::NoApplicableHistory)?
+			.operation
+			.transaction_id();
+
+		self.undo(commands)?;
+
+		if let Some(transaction_id) = transaction_id {
+			while self
+				.history
+				.peek(-1)
+				.is_some_and(|entry| entry.operation.transaction_id() == Some(transaction_id))
+			{
+				self.undo(commands)?;
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Undoes up to `count` applied operations, returning how many were undone.
+	///
+	/// Operations are undone from most-recently applied to least-recently applied, as if
+	/// [`Self::undo()`] were called `count` times. As such, this stops early at any operation
+	/// which must be [confirmed] before being undone.
+	///
+	/// # Errors
+	/// * [`Error::NoApplicableHistory`] - `count` is non-zero, but there are no operations
+	///   available to undo.
+	/// * [`Error::ConfirmationRequired`] - `count` is non-zero, but the last applied operation must
+	///   be [confirmed] before being undone.
+	///
+	/// [confirmed]: Operation::confirm_undo()
+	pub fn undo_n(&mut self, count: usize, commands: &mut Commands) -> Result<usize, Error> {
+		let mut undone_count = 0;
+		while undone_count < count {
+			match self.undo(commands) {
+				Ok(()) => undone_count += 1,
+				Err(error) if undone_count == 0 => return Err(error),
+				Err(_) => break,
+			}
+		}
+
+		Ok(undone_count)
+	}
+
+	/// Returns whether there are any applied operations, which [`Self::undo()`] could undo.
+	#[must_use]
+	pub fn can_undo(&self) -> bool {
+		self.history.peek(-1).is_some()
+	}
+
+	/// Returns whether there are any undone operations, which [`Self::redo()`] could redo.
+	///
+	/// Note that a redo may still fail if the operation fails to be reapplied.
+	#[must_use]
+	pub fn can_redo(&self) -> bool {
+		self.history.peek(1).is_some()
+	}
+
+	/// Returns `true` if there are no applied operations, such as when every operation has been
+	/// undone.
+	///
+	/// # See Also
+	/// * [`History::is_at_start()`]
+	#[must_use]
+	pub fn is_at_start(&self) -> bool {
+		self.history.is_at_start()
+	}
+
+	/// Returns `true` if there are no undone operations, and so nothing to redo.
+	///
+	/// # See Also
+	/// * [`History::is_at_end()`]
+	#[must_use]
+	pub const fn is_at_end(&self) -> bool {
+		self.history.is_at_end()
+	}
+
+	/// Returns the [weight] of the operation that [`Self::undo()`] would affect, if any.
+	///
+	/// [weight]: Operation::weight()
+	#[must_use]
+	pub fn next_undo_weight(&self) -> Option<usize> {
+		self.history.peek(-1).map(|entry| entry.operation.weight())
+	}
+
+	/// Returns the [weight] of the operation that [`Self::redo()`] would affect, if any.
+	///
+	/// [weight]: Operation::weight()
+	#[must_use]
+	pub fn next_redo_weight(&self) -> Option<usize> {
+		self.history.peek(1).map(|entry| entry.operation.weight())
+	}
+
+	/// Marks the current point in the history as the savepoint, replacing any previous savepoint.
+	/// This is typically done when the user saves their work, so that
+	/// [`Self::steps_from_savepoint()`] can tell whether there are unsaved changes.
+	pub fn set_savepoint(&mut self) {
+		self.savepoint = Some(Savepoint {
+			before: self.history.peek(-1).map(|entry| entry.sequence),
+			after: self.history.peek(1).map(|entry| entry.sequence),
+		});
+	}
+
+	/// Removes the savepoint set through [`Self::set_savepoint()`], if any.
+	pub const fn clear_savepoint(&mut self) {
+		self.savepoint = None;
+	}
+
+	/// Returns how many steps separate the current point in the history from the savepoint set
+	/// through [`Self::set_savepoint()`]. A negative number is how many undos would return to the
+	/// savepoint, and a positive number is how many redos would, such that `Some(0)` means the
+	/// history is at the savepoint.
+	///
+	/// Returns `None` if there is no savepoint, or if it can no longer be reached - such as when
+	/// the operations around it have been dropped because of the history limit, or because they
+	/// were undone and then replaced by a new operation.
+	#[must_use]
+	pub fn steps_from_savepoint(&self) -> Option<isize> {
+		let savepoint = self.savepoint.as_ref()?;
+		let position = match savepoint.before {
+			Some(before) => {
+				self.history
+					.iter()
+					.position(|entry| entry.sequence == before)?
+					+ 1
+			}
+			None => {
+				// The savepoint is at the very start of the history, which is only reachable if
+				// the operation that followed it hasn't been dropped.
+				let first = self.history.iter().next().map(|entry| entry.sequence);
+				if first != savepoint.after {
+					return None;
+				}
+				0
+			}
+		};
+		let position = isize::try_from(position).ok()?;
+		let split_index = isize::try_from(self.history.split_index()).ok()?;
+		Some(position - split_index)
+	}
+
+	/// Returns whether an undo performed now would succeed. This is the same as
+	/// [`Self::can_undo()`], except that it also returns `false` if the operation must be
+	/// [confirmed] before being undone, and is named for use alongside
+	/// [`CommandsUndoRedoExt::undo()`].
+	///
+	/// As `CommandsUndoRedoExt::undo()` only queues up an undo, its result cannot be checked
+	/// directly. Instead, input systems can read the `UndoRedo` resource and call this method to
+	/// decide whether to queue the command at all (for example, to play an error sound instead).
+	/// The answer only holds until the history next changes, so other undo/redo commands queued
+	/// in the meantime may change the outcome.
+	///
+	/// [`CommandsUndoRedoExt::undo()`]: crate::extensions::CommandsUndoRedoExt::undo()
+	/// [confirmed]: Operation::confirm_undo()
+	#[must_use]
+	pub fn would_undo_succeed(&self) -> bool {
+		self.history
+			.peek(-1)
+			.is_some_and(|entry| entry.operation.confirm_undo())
+	}
+
+	/// Returns an iterator over runs of consecutive applied operations sharing the same
+	/// [`Details::name`], from oldest to newest. Each item is the shared name, and the number of
+	/// operations in the run.
+	///
+	/// This is useful for displaying a collapsed view of the history, such as "Move Object ×5".
+	/// Undone and queued operations are not included.
+	pub fn iter_grouped_by_name(&self) -> impl Iterator<Item = (String, usize)> + '_ {
+		let mut names = self
+			.history
+			.iter_committed()
+			.map(|entry| entry.operation.details().name)
+			.peekable();
+
+		core::iter::from_fn(move || {
+			let name = names.next()?;
+			let mut count = 1;
+			while names.next_if_eq(&name).is_some() {
+				count += 1;
+			}
+			Some((name, count))
+		})
+	}
+
+	/// Returns an iterator over the details of each operation in the history, including undone
+	/// operations, from oldest to newest.
+	///
+	/// An [`OperationGroup`] is one undo step, and so appears as a single item. Use
+	/// [`Self::iter_details_expanded()`] to also list the operations within groups.
+	pub fn iter_details(&self) -> impl Iterator<Item = Details> + '_ {
+		self.history.iter().map(|entry| entry.operation.details())
+	}
+
+	/// Returns an iterator over the details and state of each operation in the history, from
+	/// oldest to newest. Applied operations come first, followed by undone operations in the order
+	/// they would be redone.
+	///
+	/// This is the data needed to display the history (i.e. in a history panel), without exposing
+	/// the operations themselves. Queued operations are not included; use
+	/// [`Self::snapshot_arc()`] to also list those.
+	pub fn iter_entries(&self) -> impl Iterator<Item = (Details, OperationState)> + '_ {
+		self.iter_stored()
+			.take_while(|(_, state, _)| *state != OperationState::Queued)
+			.map(|(operation, state, _)| (operation.details(), state))
+	}
+
+	/// Returns an iterator over the details of each operation in the history, as with
+	/// [`Self::iter_details()`], paired with the [`OperationSource`] it came from.
+	///
+	/// This is useful for filtering or styling a history panel by where operations came from.
+	pub fn iter_details_with_source(
+		&self,
+	) -> impl Iterator<Item = (Details, OperationSource)> + '_ {
+		self.history
+			.iter()
+			.map(|entry| (entry.operation.details(), entry.source))
+	}
+
+	/// Returns an iterator over the details of each operation in the history, as with
+	/// [`Self::iter_details()`], but with the operations inside each [`OperationGroup`] listed
+	/// directly after the group itself.
+	///
+	/// Each item is paired with its depth - `0` for operations in the history, `1` for operations
+	/// inside a group in the history, and so on. This is useful for displaying the history as a
+	/// tree.
+	pub fn iter_details_expanded(&self) -> impl Iterator<Item = (usize, Details)> + '_ {
+		let mut stack: Vec<(usize, &dyn Operation)> = self
+			.history
+			.iter()
+			.rev()
+			.map(|entry| (0, &*entry.operation))
+			.collect();
+
+		core::iter::from_fn(move || {
+			let (depth, operation) = stack.pop()?;
+			if let Some(group) = operation.downcast_ref::<OperationGroup>() {
+				stack.extend(group.operations().rev().map(|member| (depth + 1, member)));
+			}
+			Some((depth, operation.details()))
+		})
+	}
+
+	/// Returns the sequence number of the operation at `index`, if any.
+	///
+	/// Sequence numbers are assigned from a crate-global counter when an operation is applied, so
+	/// they give a total order of operations across every `UndoRedo`. `index` follows the same
+	/// ordering as [`History::iter()`] - applied operations from oldest to newest, followed by
+	/// undone operations from most-recently undone to least-recently undone.
+	#[must_use]
+	pub fn sequence_of(&self, index: usize) -> Option<u64> {
+		self.history.iter().nth(index).map(|entry| entry.sequence)
+	}
+
+	/// Attaches `tag` to the most recently applied operation, replacing any tag it already had.
+	///
+	/// Tags allow app-specific metadata (such as which tool created an operation) to be stored
+	/// alongside an operation, without changing the operation's type. They can be read back
+	/// through [`Self::tag_of()`]. If the operation is later coalesced with another, the merged
+	/// operation keeps this tag.
+	///
+	/// # Errors
+	/// * [`Error::NoApplicableHistory`] - There are no applied operations to tag.
+	pub fn tag_last<T: Any + Send + Sync>(&mut self, tag: T) -> Result<(), Error> {
+		let entry = self
+			.history
+			.peek_mut(-1)
+			.ok_or(Error::NoApplicableHistory)?;
+		entry.tag = Some(Box::new(tag));
+		Ok(())
+	}
+
+	/// Returns a mutable reference to the most recently applied operation, if there is one and it
+	/// is of type `O`.
+	///
+	/// This is useful for fixing up an operation just after it was applied, such as to store the
+	/// ID of an entity that was only spawned once its commands ran. Any changes are seen by the
+	/// operation when it is later undone or redone.
+	///
+	/// # See Also
+	/// * [`History::last_committed_mut()`]
+	#[must_use]
+	pub fn last_applied_mut<O: Operation>(&mut self) -> Option<&mut O> {
+		self.history.last_committed_mut()?.operation.downcast_mut()
+	}
+
+	/// Returns the tag attached to the operation at `index`, if it has one of type `T`.
+	///
+	/// `index` follows the same ordering as [`Self::sequence_of()`].
+	#[must_use]
+	pub fn tag_of<T: Any>(&self, index: usize) -> Option<&T> {
+		self.history.iter().nth(index)?.tag.as_ref()?.downcast_ref()
+	}
+
+	/// Returns the [`OperationSource`] of the operation at `index`, if any.
+	///
+	/// `index` follows the same ordering as [`Self::sequence_of()`].
+	#[must_use]
+	pub fn source_of(&self, index: usize) -> Option<OperationSource> {
+		self.history.iter().nth(index).map(|entry| entry.source)
+	}
+
+	/// Returns an immutable snapshot of the [`Details`] and [`OperationState`] of every operation
+	/// stored in this `UndoRedo`, including queued operations.
+	///
+	/// The snapshot does not contain the operations themselves, and so can be freely sent to
+	/// another thread (i.e. to be serialized in the background) while this `UndoRedo` continues to
+	/// be modified.
+	#[must_use]
+	pub fn snapshot_arc(&self) -> Arc<HistorySnapshot> {
+		let entries = self
+			.iter_stored()
+			.map(|(operation, state, _)| SnapshotEntry {
+				details: operation.details(),
+				state,
+			})
+			.collect();
+
+		Arc::new(HistorySnapshot { entries })
+	}
+
+	/// Returns a record of every operation stored in this `UndoRedo`, including queued
+	/// operations, in the same order as [`HistorySnapshot::entries()`].
+	///
+	/// This is intended to be written to a log file, such as to help reproduce a bug report.
+	#[must_use]
+	pub fn export_audit(&self) -> Vec<AuditEntry> {
+		self.iter_stored()
+			.map(|(operation, state, sequence)| AuditEntry {
+				name: operation.details().name,
+				state,
+				sequence,
+			})
+			.collect()
+	}
+
+	/// Moves all operations from `other` into `self`.
+	///
+	/// Applied operations from both are interleaved according to their sequence numbers, such that
+	/// they end up in the order they were originally applied. Queued operations from `other` are
+	/// added to the end of this `UndoRedo`'s queue. Undone operations from either side are lost,
+	/// as there is no meaningful way to combine them.
+	pub fn merge(&mut self, other: Self) {
+		self.history
+			.merge_by_key(other.history, |entry| entry.sequence);
+		self.queued_operations.extend(other.queued_operations);
+		// The merged history is a different timeline, so the savepoint no longer describes it.
+		self.savepoint = None;
+	}
+}
+
+/// Methods which immediately apply their effects to a [`World`], rather than queuing up commands.
+///
+/// These are useful in exclusive systems, or anywhere else that a `World` is available but a
+/// [`Commands`] is not.
+impl UndoRedo {
+	/// Applies `operation` to `world` immediately through [`Operation::apply_world()`], then
+	/// pushes it to the list of applied operations.
+	///
+	/// Unlike [`Self::push_and_apply()`], this allows `operation` to read from `world` while it is
+	/// being applied.
+	///
+	/// # Errors
+	/// * [`Error::InvalidOperation`] - [`Operation::validate()`] failed. The operation is not
+	///   applied.
+	/// * Any error returned by [`Operation::apply_world()`]. In this case, `operation` is dropped
+	///   without being added to the list of applied operations.
+	pub fn push_and_apply_world<O: Operation>(
+		&mut self,
+		mut operation: O,
+		world: &mut World,
+	) -> Result<(), Error> {
+		self::validate(&operation)?;
+		operation.apply_world(world)?;
+		self.commit(Box::new(operation));
+		Ok(())
+	}
+
+	/// Applies all queued operations to `world` immediately.
+	///
+	/// # Errors
+	/// See [`Self::apply_queue()`].
+	pub fn apply_queue_world(&mut self, world: &mut World) -> Result<(), Error> {
+		self::with_world_commands(world, |commands| self.apply_queue(commands))
+	}
+
+	/// Redoes the last undone operation against `world` immediately, if any.
+	///
+	/// # Errors
+	/// See [`Self::redo()`].
+	pub fn redo_world(&mut self, world: &mut World) -> Result<(), Error> {
+		self::with_world_commands(world, |commands| self.redo(commands))
+	}
+
+	/// Undoes the last applied operation against `world` immediately, if any.
+	///
+	/// # Errors
+	/// See [`Self::undo()`].
+	pub fn undo_world(&mut self, world: &mut World) -> Result<(), Error> {
+		self::with_world_commands(world, |commands| self.undo(commands))
+	}
+
+	/// Undoes up to `count` applied operations against `world` immediately, one at a time,
+	/// returning how many were undone.
+	///
+	/// This behaves the same as [`Self::undo_n()`], but if the `Events<UndoStep>` resource exists
+	/// in `world`, an [`UndoStep`] event is sent after each operation is undone. This allows
+	/// progress to be reported during a large revert.
+	///
+	/// # Errors
+	/// See [`Self::undo_n()`].
+	pub fn undo_n_world(&mut self, count: usize, world: &mut World) -> Result<usize, Error> {
+		let total = count.min(self.history.split_index());
+		let mut undone_count = 0;
+
+		while undone_count < count {
+			let name = match self.history.peek(-1) {
+				Some(entry) => entry.operation.details().name,
+				None if undone_count == 0 => return Err(Error::NoApplicableHistory),
+				None => break,
+			};
+			match self.undo_world(world) {
+				Ok(()) => undone_count += 1,
+				Err(error) if undone_count == 0 => return Err(error),
+				Err(_) => break,
+			}
+
+			if let Some(mut events) = world.get_resource_mut::<Events<UndoStep>>() {
+				events.send(UndoStep {
+					index: undone_count - 1,
+					name,
+					remaining: total.saturating_sub(undone_count),
+				});
+			}
+		}
+
+		Ok(undone_count)
+	}
+
+	/// Undoes every applied operation against `world` immediately, one at a time, returning how
+	/// many were undone. As with [`Self::undo_n_world()`], an [`UndoStep`] event is sent after
+	/// each operation is undone.
+	///
+	/// # Errors
+	/// See [`Self::undo_n()`].
+	pub fn undo_all_world(&mut self, world: &mut World) -> Result<usize, Error> {
+		self.undo_n_world(usize::MAX, world)
+	}
+}
+
+/// Private items.
+impl UndoRedo {
+	/// Calls `apply` to apply operations from the queue, returning its result along with the
+	/// [`Details`] of each queued operation that was applied, in the order they were applied.
+	fn apply_queue_reporting(
+		&mut self,
+		apply: impl FnOnce(&mut Self) -> Result<(), Error>,
+	) -> (Result<(), Error>, Vec<Details>) {
+		// Operations are taken from the queue in order, so the details of those that get applied
+		// can be found by how many were taken.
+		let queued = self
+			.queued_operations
+			.iter()
+			.map(|queued| queued.operation.details());
+		let mut applied: Vec<Details> = match self.queue_order {
+			QueueOrder::Fifo => queued.collect(),
+			QueueOrder::Lifo => queued.rev().collect(),
+		};
+
+		let result = apply(self);
+
+		// A failing operation is taken from the queue, but is dropped rather than applied. If the
+		// queue was empty to begin with, nothing was taken.
+		let taken = applied.len() - self.queued_operations.len();
+		applied.truncate(if result.is_err() {
+			taken.saturating_sub(1)
+		} else {
+			taken
+		});
+
+		(result, applied)
+	}
+
+	/// Returns an iterator over every operation stored in this `UndoRedo`, in the order described
+	/// by [`HistorySnapshot::entries()`], along with its state and its sequence number (if it has
+	/// been applied).
+	fn iter_stored(
+		&self,
+	) -> impl Iterator<Item = (&dyn Operation, OperationState, Option<u64>)> + '_ {
+		let applied = self.history.iter_committed().map(|entry| {
+			(
+				&*entry.operation,
+				OperationState::Applied,
+				Some(entry.sequence),
+			)
+		});
+		let undone = self.history.iter_undone().map(|entry| {
+			(
+				&*entry.operation,
+				OperationState::Undone,
+				Some(entry.sequence),
+			)
+		});
+		let queued = self
+			.queued_operations
+			.iter()
+			.map(|queued| (&*queued.operation, OperationState::Queued, None));
+		applied.chain(undone).chain(queued)
+	}
+
+	/// Removes the next queued operation to be applied, according to [`Self::queue_order()`].
+	fn pop_queued(&mut self) -> Option<QueuedOperation> {
+		match self.queue_order {
+			QueueOrder::Fifo => self.queued_operations.pop_front(),
+			QueueOrder::Lifo => self.queued_operations.pop_back(),
+		}
+	}
+
+	/// Queues up the commands needed to apply `operation`, then pushes it to the list of applied
+	/// operations if it was successfully applied, recording that it came from `source`.
+	fn apply_and_commit(
+		&mut self,
+		mut operation: Box<dyn Operation>,
+		source: OperationSource,
+		commands: &mut Commands,
+		context: &mut OperationContext,
+	) -> Result<(), Error> {
+		operation.apply_in_context(commands, context)?;
+		self.commit_from(operation, source);
+		Ok(())
+	}
+
+	/// Pushes the already-applied operations in `run` to the list of applied operations, as one
+	/// [`OperationGroup`] if there is more than one.
+	fn commit_run(&mut self, run: Option<LocalityRun>) {
+		let Some(LocalityRun {
+			mut operations,
+			source,
+			..
+		}) = run
+		else {
+			return;
+		};
+
+		if operations.len() == 1 {
+			self.commit_from(operations.remove(0), source);
+		} else if let Some(first) = operations.first() {
+			let mut group = OperationGroup::new(first.details());
+			for operation in operations {
+				group.push_boxed(operation);
+			}
+			self.commit_from(Box::new(group), source);
+		}
+	}
+
+	/// Pushes an already-applied operation to the list of applied operations, merging it into the
+	/// previously-applied operation if the coalesce policy allows, or cancelling both out if it
+	/// inverts the previous one and [`Self::cancel_inverses`] is set.
+	fn commit(&mut self, operation: Box<dyn Operation>) {
+		self.commit_from(operation, OperationSource::User);
+	}
+
+	/// Pushes an already-applied operation to the list of applied operations, as with
+	/// [`Self::commit()`], recording that it came from `source`.
+	fn commit_from(&mut self, operation: Box<dyn Operation>, source: OperationSource) {
+		self.commit_with(operation, true, source);
+	}
+
+	/// Pushes an already-applied operation to the list of applied operations, as with
+	/// [`Self::commit_from()`]. If `coalesce` is `false`, the operation is always recorded as a new
+	/// entry, and later operations are never merged into it.
+	fn commit_with(
+		&mut self,
+		operation: Box<dyn Operation>,
+		coalesce: bool,
+		source: OperationSource,
+	) {
+		if let Some(batch) = &mut self.batch {
+			batch.push_boxed(operation);
+			return;
+		}
+
+		let was_at_start = self.history.split_index() == 0;
+		self.commit_inner(operation, coalesce, source);
+
+		if let Some(savepoint) = &mut self.savepoint {
+			if was_at_start && savepoint.before.is_none() {
+				// Any undone operations were discarded, so the savepoint at the start of the
+				// history is now followed by the new operation instead.
+				savepoint.after = self.history.iter().next().map(|entry| entry.sequence);
+			}
+		}
+	}
+
+	/// Removes the savepoint if it directly follows the operation with the given sequence number.
+	fn invalidate_savepoint_at(&mut self, sequence: u64) {
+		if self
+			.savepoint
+			.as_ref()
+			.is_some_and(|savepoint| savepoint.before == Some(sequence))
+		{
+			self.savepoint = None;
+		}
+	}
+
+	/// Adds `operation` to the previously-applied entry, which has been popped from the history
+	/// as `previous`, such that both are undone as one step.
+	///
+	/// If `previous` was itself made by grouping operations, `operation` is added to that group.
+	/// Otherwise, both are placed in a new [`OperationGroup`], named after the previous operation.
+	fn group_with_previous(&mut self, previous: HistoryEntry, operation: Box<dyn Operation>) {
+		self.invalidate_savepoint_at(previous.sequence);
+		let mut group = match previous.operation.downcast::<OperationGroup>() {
+			Ok(group) if previous.auto_group => group,
+			first => {
+				let first: Box<dyn Operation> = match first {
+					Ok(group) => group,
+					Err(first) => first,
+				};
+				let mut group = Box::new(OperationGroup::new(first.details()));
+				group.push_boxed(first);
+				group
+			}
+		};
+		group.push_boxed(operation);
+		self.history.restore_committed(HistoryEntry {
+			operation: group,
+			auto_group: true,
+			..previous
+		});
+	}
+
+	/// Records `operation` as [`Self::commit_with()`] does, without updating the savepoint.
+	///
+	/// If `operation` is merged or grouped into the previous entry, that entry keeps its own
+	/// source.
+	fn commit_inner(
+		&mut self,
+		operation: Box<dyn Operation>,
+		coalesce: bool,
+		source: OperationSource,
+	) {
+		if self.history.mode == HistoryMode::Disabled {
+			// The operation won't be recorded, so it must not be merged or grouped into the last
+			// recorded entry either. Pushing still discards any undone operations.
+			self.history.push(HistoryEntry::new(operation));
+			return;
+		}
+
+		let can_coalesce = coalesce
+			&& self
+				.history
+				.peek(-1)
+				.is_some_and(|previous| previous.coalesce);
+		let previous = if can_coalesce {
+			self.history.pop_committed()
+		} else {
+			None
+		};
+		let Some(previous) = previous else {
+			self.history.push(HistoryEntry {
+				coalesce,
+				frame: self.current_frame,
+				source,
+				..HistoryEntry::new(operation)
+			});
+			return;
+		};
+
+		if self.current_frame.is_some() && previous.frame == self.current_frame {
+			// Both operations were applied in the same frame, so they become one undo step.
+			self.group_with_previous(previous, operation);
+			return;
+		}
+
+		if self.cancel_inverses && operation.inverts(&*previous.operation) {
+			// Both operations have been applied, and so the World is back where it started. Any
+			// undone operations are still lost, as they would be if `operation` were recorded.
+			self.history.evict_undone();
+			return;
+		}
+
+		// If the previous entry is already a group of matching operations, the new operation is
+		// compared against the last of them.
+		let last_previous = match previous.operation.downcast_ref::<OperationGroup>() {
+			Some(group) if previous.auto_group => group.operations().next_back(),
+			_ => Some(&*previous.operation),
+		};
+		if last_previous.is_some_and(|last| self.coalesce_by.matches(last, &*operation)) {
+			self.group_with_previous(previous, operation);
+			return;
+		}
+
+		let result = match &self.coalesce_policy {
+			Some(policy) => policy.coalesce(previous.operation, operation),
+			None => previous.operation.merge_into(operation),
+		};
+		match result {
+			Ok(merged) => {
+				// The merged operation takes the place of the previous one, so it keeps the
+				// previous one's data. It no longer ends at the same point, however.
+				self.invalidate_savepoint_at(previous.sequence);
+				self.history.restore_committed(HistoryEntry {
+					operation: merged,
+					..previous
+				});
+			}
+			Err((previous_operation, operation)) => {
+				self.history.restore_committed(HistoryEntry {
+					operation: previous_operation,
+					..previous
+				});
+				self.history.push(HistoryEntry {
+					frame: self.current_frame,
+					source,
+					..HistoryEntry::new(operation)
+				});
+			}
+		}
+	}
+}
+
+/// Checks that `operation` is valid, converting any failure into an [`Error`].
+fn validate(operation: &dyn Operation) -> Result<(), Error> {
+	operation.validate().map_err(Error::InvalidOperation)
+}
+
+/// Creates a [`Commands`] for `world` and calls `f` with it, then applies any commands queued by
+/// `f` to `world`.
+pub(crate) fn with_world_commands<R>(world: &mut World, f: impl FnOnce(&mut Commands) -> R) -> R {
+	let mut command_queue = CommandQueue::default();
+	let mut commands = Commands::new(&mut command_queue, world);
+	let result = f(&mut commands);
+	command_queue.apply(world);
+	result
+}
+
+/// A run of consecutive operations with the same [locality key], which have been applied but not
+/// yet committed.
+///
+/// [locality key]: Operation::locality_key()
+struct LocalityRun {
+	/// The locality key shared by every operation in this run.
+	key: u64,
+	/// The source shared by every operation in this run.
+	source: OperationSource,
+	/// The operations in this run, in the order they were applied.
+	operations: Vec<Box<dyn Operation>>,
+}
+
+/// A point in an [`UndoRedo`]'s history, as marked by [`UndoRedo::set_savepoint()`].
+///
+/// The point is remembered by the sequence numbers of the operations on either side of it, so
+/// that it stays put as operations are undone, redone, and dropped.
+struct Savepoint {
+	/// The sequence number of the operation directly before the savepoint, or `None` if the
+	/// savepoint is at the start of the history.
+	before: Option<u64>,
+	/// The sequence number of the operation directly after the savepoint, if any.
+	after: Option<u64>,
+}
+
+/// An operation waiting in an [`UndoRedo`]'s queue, along with where it came from.
+struct QueuedOperation {
+	/// The operation itself.
+	operation: Box<dyn Operation>,
+	/// Where the operation came from.
+	source: OperationSource,
+}
+
+/// The next sequence number to be given to an applied operation.
+static NEXT_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+/// An operation stored in an [`UndoRedo`]'s history, along with any data tracked about it.
+struct HistoryEntry {
+	/// The operation itself.
+	operation: Box<dyn Operation>,
+	/// The sequence number given to this operation when it was applied.
+	sequence: u64,
+	/// User data attached to this entry through [`UndoRedo::tag_last()`], if any.
+	tag: Option<Box<dyn Any + Send + Sync>>,
+	/// Whether the operation's effects are currently applied. This guards against undoing or
+	/// redoing the same operation twice in a row, which would otherwise corrupt the World.
+	applied: bool,
+	/// Whether later operations may be merged into (or cancel out) this entry. This is `false`
+	/// for operations pushed through [`UndoRedo::push_no_coalesce()`].
+	coalesce: bool,
+	/// The frame in which this entry was recorded, if operations were being grouped by frame at
+	/// the time. See [`UndoRedo::set_current_frame()`].
+	frame: Option<u64>,
+	/// Whether this entry's operation is an [`OperationGroup`] made by grouping operations by
+	/// frame or by [`CoalesceKey`], such that further operations can be added to it.
+	auto_group: bool,
+	/// Where the operation came from.
+	source: OperationSource,
+}
+
+impl HistoryEntry {
+	/// Creates a new `HistoryEntry` for an operation, giving it the next sequence number.
+	fn new(operation: Box<dyn Operation>) -> Self {
+		Self {
+			operation,
+			sequence: NEXT_SEQUENCE.fetch_add(1, Ordering::Relaxed),
+			tag: None,
+			applied: true,
+			coalesce: true,
+			frame: None,
+			auto_group: false,
+			source: OperationSource::User,
+		}
+	}
+
+	/// Queues up the commands needed to undo this entry's operation, unless it has already been
+	/// undone.
+	fn undo(&mut self, commands: &mut Commands) {
+		if self.applied {
+			self.operation.undo(commands);
+			self.applied = false;
+		}
+	}
+
+	/// Queues up the commands needed to reapply this entry's operation, unless it is already
+	/// applied.
+	///
+	/// # Errors
+	/// Any error returned by [`Operation::apply_in_context()`], in which case the entry is still
+	/// considered undone.
+	fn reapply(&mut self, commands: &mut Commands) -> Result<(), Error> {
+		if !self.applied {
+			self.operation
+				.apply_in_context(commands, &mut OperationContext::new())?;
+			self.applied = true;
+		}
+		Ok(())
+	}
+}
+
+/// The outcome of an undo performed through [`UndoRedo::try_undo()`].
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum UndoOutcome {
+	/// The last applied operation was undone.
+	Performed,
+	/// There were no applied operations to undo.
+	NothingToUndo,
+	/// The last applied operation must be [confirmed] before being undone, and so was not undone.
+	///
+	/// [confirmed]: Operation::confirm_undo()
+	ConfirmationRequired,
+}
+
+/// The order in which an [`UndoRedo`]'s queued operations are applied.
+///
+/// This can be set through [`UndoRedo::set_queue_order()`].
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub enum QueueOrder {
+	/// Operations are applied in the order they were queued.
+	#[default]
+	Fifo,
+	/// The most recently queued operation is applied first. This is useful for tools which
+	/// generate operations in reverse dependency order.
+	Lifo,
+}
+
+/// Where an operation stored in an [`UndoRedo`] came from.
+///
+/// This is recorded through [`UndoRedo::push_to_queue_from()`], and can be read back through
+/// [`UndoRedo::source_of()`]. Operations pushed by any other means are assumed to come from
+/// [`OperationSource::User`].
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub enum OperationSource {
+	/// The operation was performed by the user, such as through a tool or a menu.
+	#[default]
+	User,
+	/// The operation was performed by a game system, rather than in response to the user.
+	System,
+	/// The operation was performed by a script.
+	Script,
+}
+
+/// Controls how [`apply_queued_operations`] records the operations it applies.
+///
+/// Insert this as a resource to change the behavior of `apply_queued_operations`. If this resource
+/// is not present, [`QueueFlushMode::Individual`] is used.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, Resource)]
+pub enum QueueFlushMode {
+	/// Each applied operation is recorded on its own, and so is undone on its own.
+	#[default]
+	Individual,
+	/// All operations applied in a single run of the system are recorded together as one
+	/// [`OperationGroup`], and so are undone together.
+	Grouped,
+}
+
+/// Applies any queued operations when this system is run.
+///
+/// How the applied operations are recorded can be changed by inserting a [`QueueFlushMode`]
+/// resource.
+///
+/// If there are no queued operations, the [`UndoRedo`] resource is left untouched, and so is not
+/// marked as changed.
+///
+/// To do more after the queue is applied, such as sending events, write your own system which
+/// takes a [`ResMut<UndoRedo>`] alongside whichever other system parameters you need, and calls
+/// [`UndoRedo::apply_queue()`] (or one of its variants) itself. [`apply_queue_and_notify`] is an
+/// example of this, and can be used in place of this system.
+pub fn apply_queued_operations(
+	undoredo: ResMut<UndoRedo>,
+	flush_mode: Option<Res<QueueFlushMode>>,
+	mut commands: Commands,
+) {
+	flush_queue(undoredo, flush_mode, &mut commands, |_| {});
+}
+
+/// An [`Event`] sent by [`apply_queue_and_notify`] for each operation it applies.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Event)]
+pub struct OperationApplied {
+	/// The applied operation's details, at the time it was applied.
+	pub details: Details,
+}
+
+/// An [`Event`] sent by [`UndoRedo::undo_with_reason()`] for the operation it undoes.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Event)]
+pub struct UndoPerformed {
+	/// The undone operation's details, at the time it was undone.
+	pub details: Details,
+	/// Why the operation was undone.
+	pub reason: String,
+}
+
+/// An [`Event`] sent by [`UndoRedo::undo_n_world()`] and [`UndoRedo::undo_all_world()`] for each
+/// operation they undo.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Event)]
+pub struct UndoStep {
+	/// How many operations were undone before this one, in the same call.
+	pub index: usize,
+	/// The name of the undone operation.
+	pub name: String,
+	/// How many more operations are expected to be undone in the same call.
+	pub remaining: usize,
+}
+
+/// Applies any queued operations when this system is run, as with [`apply_queued_operations`],
+/// then sends an [`OperationApplied`] event for each operation that was applied, in the order they
+/// were applied.
+///
+/// Operations which fail to apply do not send an event. The `OperationApplied` event must be
+/// registered (i.e. through `App::add_event()`) for this system to run.
+pub fn apply_queue_and_notify(
+	undoredo: ResMut<UndoRedo>,
+	flush_mode: Option<Res<QueueFlushMode>>,
+	mut commands: Commands,
+	mut events: EventWriter<OperationApplied>,
+) {
+	flush_queue(undoredo, flush_mode, &mut commands, |details| {
+		events.send(OperationApplied { details });
+	});
+}
+
+/// Applies any queued operations according to `flush_mode`, then calls `on_applied` with the
+/// details of each operation that was applied, in the order they were applied.
+///
+/// This drives the systems which apply the queue, such as [`apply_queued_operations`].
+fn flush_queue(
+	mut undoredo: ResMut<UndoRedo>,
+	flush_mode: Option<Res<QueueFlushMode>>,
+	commands: &mut Commands,
+	on_applied: impl FnMut(Details),
+) {
+	// Check through an immutable reference first, so that change detection isn't triggered when
+	// there's nothing to do.
+	if undoredo.queued_operations.is_empty() {
+		return;
+	}
+
+	let flush_mode = flush_mode.map_or_else(QueueFlushMode::default, |flush_mode| *flush_mode);
+
+	// We intentionally ignore the result, as we don't care how much work was done.
+	let (_, applied) = undoredo.apply_queue_reporting(|undoredo| match flush_mode {
+		QueueFlushMode::Individual => undoredo.apply_queue(commands),
+		QueueFlushMode::Grouped => {
+			let group = OperationGroup::default();
+			undoredo.apply_queue_into_group(group, commands)
+		}
+	});
+	applied.into_iter().for_each(on_applied);
+}
+
+/// Applies a new, default instance of `O` when this system is run, and records it in the
+/// [`UndoRedo`] resource.
+///
+/// This allows operations which need no input to be scheduled directly - for example, adding
+/// `run_operation_as_system::<ToggleGrid>` with a run condition checking for a key press.
+///
+/// As with [`apply_queued_operations`], any error returned while applying the operation is
+/// ignored.
+pub fn run_operation_as_system<O: Operation + Default>(
+	mut undoredo: ResMut<UndoRedo>,
+	mut commands: Commands,
+) {
+	let _ = undoredo.push_and_apply(O::default(), &mut commands);
+}
+
+/// Asserts that the [`Details::name`]s of the operations in an [`UndoRedo`]'s history, including
+/// undone operations, are equal to the given list of names, from oldest to newest.
+///
+/// For example, `assert_history_names!(undoredo, ["Move", "Rotate"])`.
+#[cfg(any(test, feature = "test-utils"))]
+#[macro_export]
+macro_rules! assert_history_names {
+	($undoredo:expr, [$($name:expr),* $(,)?]) => {
+		::core::assert_eq!(
+			$undoredo
+				.iter_details()
+				.map(|details| details.name)
+				.collect::<::std::vec::Vec<_>>(),
+			::std::vec![$(::std::string::String::from($name)),*],
+		)
+	};
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{
+		coalescing::CoalesceResult,
+		extensions::CommandsUndoRedoExt,
+		test_fixtures::{AddToCounter, Counter},
+	};
+	use bevy_ecs::{component::Component, entity::Entity, world::Mut};
+	use bevy_ecs::{schedule::Schedule, system::RunSystemOnce};
+	use rstest::rstest;
+	use std::sync::Mutex;
+
+	#[derive(Component)]
+	struct Marker;
+
+	#[derive(Default)]
+	struct SpawnEntity(Option<Entity>);
+
+	impl Operation for SpawnEntity {
+		fn details(&self) -> Details {
+			Details::default()
+		}
+
+		fn apply(&mut self, commands: &mut Commands) {
+			let _ = self.apply_in_context(commands, &mut OperationContext::new());
+		}
+
+		fn apply_in_context(
+			&mut self,
+			commands: &mut Commands,
+			context: &mut OperationContext,
+		) -> Result<(), Error> {
+			let entity = commands.spawn_empty().id();
+			context.push_spawned(entity);
+			self.0 = Some(entity);
+			Ok(())
+		}
+
+		fn undo(&self, commands: &mut Commands) {
+			if let Some(entity) = self.0 {
+				commands.entity(entity).despawn();
+			}
+		}
+	}
+
+	/// Despawns the contained entity when undone, if any.
+	struct DespawnOnUndo(Option<Entity>);
+
+	impl Operation for DespawnOnUndo {
+		fn apply(&mut self, _commands: &mut Commands) {}
+
+		fn undo(&self, commands: &mut Commands) {
+			if let Some(entity) = self.0 {
+				commands.entity(entity).despawn();
+			}
+		}
+	}
+
+	#[rstest]
+	fn last_applied_operation_can_be_patched() {
+		let mut world = World::new();
+		let mut undoredo = UndoRedo::new();
+		assert!(undoredo.last_applied_mut::<DespawnOnUndo>().is_none());
+
+		undoredo
+			.push_and_apply_world(DespawnOnUndo(None), &mut world)
+			.unwrap();
+		let entity = world.spawn_empty().id();
+		assert!(undoredo.last_applied_mut::<SpawnEntity>().is_none());
+		undoredo.last_applied_mut::<DespawnOnUndo>().unwrap().0 = Some(entity);
+
+		undoredo.undo_world(&mut world).unwrap();
+		assert_eq!(world.iter_entities().count(), 0);
+	}
+
+	#[derive(Default)]
+	struct MarkLastSpawned(Option<Entity>);
+
+	impl Operation for MarkLastSpawned {
+		fn details(&self) -> Details {
+			Details::default()
+		}
+
+		fn apply(&mut self, commands: &mut Commands) {
+			let _ = self.apply_in_context(commands, &mut OperationContext::new());
+		}
+
+		fn apply_in_context(
+			&mut self,
+			commands: &mut Commands,
+			context: &mut OperationContext,
+		) -> Result<(), Error> {
+			self.0 = self.0.or(context.last_spawned());
+			if let Some(entity) = self.0 {
+				commands.entity(entity).insert(Marker);
+			}
+			Ok(())
+		}
+
+		fn undo(&self, commands: &mut Commands) {
+			if let Some(entity) = self.0 {
+				commands.entity(entity).remove::<Marker>();
+			}
+		}
+	}
+
+	#[rstest]
+	fn apply_queue_shares_context() {
+		let mut world = World::new();
+		let mut undoredo = UndoRedo::default();
+		undoredo.push_to_queue(SpawnEntity::default()).unwrap();
+		undoredo.push_to_queue(MarkLastSpawned::default()).unwrap();
+
+		with_world_commands(&mut world, |commands| undoredo.apply_queue(commands)).unwrap();
+
+		let mut query = world.query::<&Marker>();
+		assert_eq!(query.iter(&world).count(), 1);
+	}
+
+	/// An operation which always fails to apply.
+	struct AlwaysFails;
+
+	impl Operation for AlwaysFails {
+		fn apply(&mut self, _commands: &mut Commands) {}
+
+		fn apply_in_context(
+			&mut self,
+			_commands: &mut Commands,
+			_context: &mut OperationContext,
+		) -> Result<(), Error> {
+			Err(Error::OperationFailed("always fails".into()))
+		}
+
+		fn undo(&self, _commands: &mut Commands) {}
+	}
+
+	#[rstest]
+	fn apply_queue_with_reports_failures_and_continues() {
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		let mut undoredo = UndoRedo::new();
+		undoredo.push_to_queue(AddToCounter(1)).unwrap();
+		undoredo.push_to_queue(AlwaysFails).unwrap();
+		undoredo.push_to_queue(AddToCounter(2)).unwrap();
+
+		let mut failures = vec![];
+		with_world_commands(&mut world, |commands| {
+			undoredo.apply_queue_with(commands, |details, error| {
+				failures.push((details.name.clone(), error.to_string()));
+			})
+		})
+		.unwrap();
+
+		assert_eq!(failures.len(), 1);
+		assert_eq!(failures[0].0, "AlwaysFails");
+		assert_eq!(world.resource::<Counter>().0, 3);
+		assert_eq!(undoredo.history.iter().count(), 2);
+	}
+
+	/// An operation which panics when applied.
+	struct Panics;
+
+	impl Operation for Panics {
+		fn apply(&mut self, _commands: &mut Commands) {
+			panic!("applied an operation which panics");
+		}
+
+		fn undo(&self, _commands: &mut Commands) {}
+	}
+
+	#[rstest]
+	fn apply_queue_failure_keeps_later_operations_queued() {
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		let mut undoredo = UndoRedo::new();
+		undoredo.push_to_queue(AddToCounter(1)).unwrap();
+		undoredo.push_to_queue(AlwaysFails).unwrap();
+		undoredo.push_to_queue(AddToCounter(2)).unwrap();
+		undoredo.push_to_queue(Panics).unwrap();
+		undoredo.push_to_queue(AddToCounter(4)).unwrap();
+
+		let result = with_world_commands(&mut world, |commands| undoredo.apply_queue(commands));
+		assert_eq!(result, Err(Error::OperationFailed("always fails".into())));
+		assert_eq!(world.resource::<Counter>().0, 1);
+		assert_eq!(undoredo.queued_operations.len(), 3);
+
+		let result = std::panic::catch_unwind(core::panic::AssertUnwindSafe(|| {
+			with_world_commands(&mut world, |commands| undoredo.apply_queue(commands))
+		}));
+		assert!(result.is_err());
+		assert_eq!(undoredo.queued_operations.len(), 1);
+
+		// The panic also discarded the commands queued before it, so only the last operation's
+		// change is seen here.
+		with_world_commands(&mut world, |commands| undoredo.apply_queue(commands)).unwrap();
+		assert_eq!(world.resource::<Counter>().0, 5);
+		assert!(undoredo.queued_operations.is_empty());
+	}
+
+	#[rstest]
+	fn apply_queue_counts_discarded_undone_operations() {
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		let mut undoredo = UndoRedo::new();
+		let mut discarded = usize::MAX;
+
+		with_world_commands(&mut world, |commands| {
+			for amount in [1, 2, 4] {
+				undoredo.push_and_apply(AddToCounter(amount), commands)?;
+			}
+			undoredo.undo(commands)?;
+			undoredo.undo(commands)
+		})
+		.unwrap();
+
+		undoredo.push_to_queue(AddToCounter(8)).unwrap();
+		with_world_commands(&mut world, |commands| {
+			undoredo.apply_queue_counting_discarded(commands, &mut discarded)
+		})
+		.unwrap();
+		assert_eq!(discarded, 2);
+		assert!(!undoredo.can_redo());
+
+		let result = with_world_commands(&mut world, |commands| {
+			undoredo.apply_queue_counting_discarded(commands, &mut discarded)
+		});
+		assert_eq!(result, Err(Error::NoQueuedOperations));
+		assert_eq!(discarded, 0);
+	}
+
+	#[rstest]
+	fn snapshot_is_independent_of_later_changes() {
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		let mut undoredo = UndoRedo::new();
+
+		with_world_commands(&mut world, |commands| {
+			undoredo.push_and_apply(AddToCounter(1), commands)?;
+			undoredo.push_and_apply(AddToCounter(2), commands)?;
+			undoredo.undo(commands)
+		})
+		.unwrap();
+		undoredo.push_to_queue(AddToCounter(3)).unwrap();
+
+		let snapshot = undoredo.snapshot_arc();
+		undoredo.clear();
+
+		let states: Vec<_> = snapshot.entries().iter().map(|entry| entry.state).collect();
+		assert_eq!(
+			states,
+			[
+				OperationState::Applied,
+				OperationState::Undone,
+				OperationState::Queued
+			]
+		);
+	}
+
+	#[rstest]
+	fn audit_reflects_applies_and_undos() {
+		let mut world = World::new();
+		let mut undoredo = UndoRedo::new();
+		let named = |name: &str| {
+			OperationGroup::new(Details {
+				name: name.to_owned(),
+			})
+		};
+
+		with_world_commands(&mut world, |commands| {
+			undoredo.push_and_apply(named("Create"), commands)?;
+			undoredo.push_and_apply(named("Move"), commands)?;
+			undoredo.push_and_apply(named("Delete"), commands)?;
+			undoredo.undo(commands)?;
+			undoredo.undo(commands)
+		})
+		.unwrap();
+		undoredo.push_to_queue(named("Rename")).unwrap();
+
+		let audit = undoredo.export_audit();
+		let records: Vec<_> = audit
+			.iter()
+			.map(|entry| (entry.name.as_str(), entry.state))
+			.collect();
+		assert_eq!(
+			records,
+			[
+				("Create", OperationState::Applied),
+				("Move", OperationState::Undone),
+				("Delete", OperationState::Undone),
+				("Rename", OperationState::Queued),
+			]
+		);
+		let sequences: Vec<_> = audit.iter().map(|entry| entry.sequence).collect();
+		assert!(sequences[..3].iter().all(Option::is_some));
+		assert!(sequences[..3].is_sorted());
+		assert_eq!(sequences[3], None);
+	}
+
+	#[rstest]
+	fn iter_entries_lists_details_and_states_in_timeline_order() {
+		let named = |name: &str| -> Box<dyn Operation> {
+			Box::new(OperationGroup::new(Details {
+				name: name.to_owned(),
+			}))
+		};
+		let mut history: History<_> = ["a", "b", "c", "d"].into_iter().map(named).collect();
+		history.undo().unwrap();
+		history.undo().unwrap();
+		let mut undoredo = UndoRedo::with_history(history);
+		undoredo.push_to_queue(OperationGroup::default()).unwrap();
+
+		let entries: Vec<_> = undoredo
+			.iter_entries()
+			.map(|(details, state)| (details.name, state))
+			.collect();
+		assert_eq!(
+			entries,
+			[
+				("a".to_owned(), OperationState::Applied),
+				("b".to_owned(), OperationState::Applied),
+				("c".to_owned(), OperationState::Undone),
+				("d".to_owned(), OperationState::Undone),
+			]
+		);
+	}
+
+	#[rstest]
+	fn with_history_can_undo_immediately() {
+		let mut world = World::new();
+		world.insert_resource(Counter(3));
+
+		let history: History<Box<dyn Operation>> = [1, 2]
+			.map(|amount| Box::new(AddToCounter(amount)) as _)
+			.into_iter()
+			.collect();
+		let mut undoredo = UndoRedo::with_history(history);
+
+		with_world_commands(&mut world, |commands| undoredo.undo(commands)).unwrap();
+		assert_eq!(world.resource::<Counter>().0, 1);
+	}
+
+	#[rstest]
+	#[case::fifo(QueueOrder::Fifo, 123)]
+	#[case::lifo(QueueOrder::Lifo, 321)]
+	fn queue_order_controls_application_order(#[case] order: QueueOrder, #[case] expected: i32) {
+		/// Appends the contained digit to the end of [`Counter`].
+		struct AppendDigit(i32);
+
+		impl Operation for AppendDigit {
+			fn apply(&mut self, commands: &mut Commands) {
+				let digit = self.0;
+				commands.queue(move |world: &mut World| {
+					let mut counter = world.resource_mut::<Counter>();
+					counter.0 = counter.0 * 10 + digit;
+				});
+			}
+
+			fn undo(&self, commands: &mut Commands) {
+				commands.queue(|world: &mut World| world.resource_mut::<Counter>().0 /= 10);
+			}
+		}
+
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		let mut undoredo = UndoRedo::new();
+		undoredo.set_queue_order(order);
+		for digit in 1..=3 {
+			undoredo.push_to_queue(AppendDigit(digit)).unwrap();
+		}
+
+		with_world_commands(&mut world, |commands| undoredo.apply_queue(commands)).unwrap();
+		assert_eq!(world.resource::<Counter>().0, expected);
+	}
+
+	#[rstest]
+	fn iter_grouped_by_name_collapses_runs() {
+		let named = |name: &str| {
+			OperationGroup::new(Details {
+				name: name.to_owned(),
+			})
+		};
+
+		let mut world = World::new();
+		let mut undoredo = UndoRedo::new();
+		with_world_commands(&mut world, |commands| {
+			for name in ["Move", "Move", "Rotate", "Move", "Move", "Move", "Delete"] {
+				undoredo.push_and_apply(named(name), commands)?;
+			}
+			undoredo.undo(commands)
+		})
+		.unwrap();
+
+		let groups: Vec<_> = undoredo.iter_grouped_by_name().collect();
+		assert_eq!(
+			groups,
+			[
+				("Move".to_owned(), 2),
+				("Rotate".to_owned(), 1),
+				("Move".to_owned(), 3)
+			]
+		);
+	}
+
+	#[rstest]
+	fn undoing_an_undone_entry_again_does_nothing() {
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		let mut undoredo = UndoRedo::new();
+
+		with_world_commands(&mut world, |commands| {
+			undoredo.push_and_apply(AddToCounter(5), commands)?;
+			undoredo.undo(commands)
+		})
+		.unwrap();
+		assert_eq!(world.resource::<Counter>().0, 0);
+
+		// Move the entry back without reapplying it, as if the history had gotten out of sync
+		// with the World, then undo it again.
+		undoredo.history.redo().unwrap();
+		with_world_commands(&mut world, |commands| undoredo.undo(commands)).unwrap();
+		assert_eq!(world.resource::<Counter>().0, 0);
+
+		with_world_commands(&mut world, |commands| undoredo.redo(commands)).unwrap();
+		assert_eq!(world.resource::<Counter>().0, 5);
+	}
+
+	#[rstest]
+	fn apply_queue_groups_runs_by_locality_key() {
+		/// Paints a tile in a chunk, by adding 1 to [`Counter`].
+		struct PaintTile {
+			chunk: u64,
+		}
+
+		impl Operation for PaintTile {
+			fn locality_key(&self) -> Option<u64> {
+				Some(self.chunk)
+			}
+
+			fn apply(&mut self, commands: &mut Commands) {
+				commands.queue(|world: &mut World| world.resource_mut::<Counter>().0 += 1);
+			}
+
+			fn undo(&self, commands: &mut Commands) {
+				commands.queue(|world: &mut World| world.resource_mut::<Counter>().0 -= 1);
+			}
+		}
+
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		let mut undoredo = UndoRedo::new();
+		for chunk in [0, 0, 0, 1, 1] {
+			undoredo.push_to_queue(PaintTile { chunk }).unwrap();
+		}
+
+		with_world_commands(&mut world, |commands| undoredo.apply_queue(commands)).unwrap();
+		assert_eq!(world.resource::<Counter>().0, 5);
+		assert_eq!(undoredo.history.iter_committed().count(), 2);
+
+		with_world_commands(&mut world, |commands| undoredo.undo(commands)).unwrap();
+		assert_eq!(world.resource::<Counter>().0, 3);
+	}
+
+	#[rstest]
+	fn change_detection_ignores_no_ops() {
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		world.init_resource::<UndoRedo>();
+		let changed_after = |world: &mut World, system: fn(Commands)| {
+			world.clear_trackers();
+			world.run_system_once(system).unwrap();
+			world.run_system_once(apply_queued_operations).unwrap();
+			world.is_resource_changed::<UndoRedo>()
+		};
+
+		assert!(!changed_after(&mut world, |_| {}));
+		assert!(!changed_after(&mut world, |mut commands| commands.undo()));
+		assert!(!changed_after(&mut world, |mut commands| commands.redo()));
+
+		world
+			.resource_mut::<UndoRedo>()
+			.push_to_queue(AddToCounter(1))
+			.unwrap();
+		assert!(changed_after(&mut world, |_| {}));
+		assert!(changed_after(&mut world, |mut commands| commands.undo()));
+		assert!(changed_after(&mut world, |mut commands| commands.redo()));
+		assert_eq!(world.resource::<Counter>().0, 1);
+	}
+
+	#[rstest]
+	fn can_undo_and_redo_reflect_history() {
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		let mut undoredo = UndoRedo::new();
+		let state = |undoredo: &UndoRedo| {
+			(
+				undoredo.can_undo(),
+				undoredo.would_undo_succeed(),
+				undoredo.can_redo(),
+			)
+		};
+		assert_eq!(state(&undoredo), (false, false, false));
+
+		undoredo.push_to_queue(AddToCounter(1)).unwrap();
+		assert_eq!(state(&undoredo), (false, false, false));
+
+		with_world_commands(&mut world, |commands| undoredo.apply_queue(commands)).unwrap();
+		assert_eq!(state(&undoredo), (true, true, false));
+
+		with_world_commands(&mut world, |commands| undoredo.undo(commands)).unwrap();
+		assert_eq!(state(&undoredo), (false, false, true));
+	}
+
+	#[rstest]
+	fn reset_drops_operations_but_keeps_configuration() {
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		let mut undoredo = UndoRedoBuilder::new()
+			.limit(NonZeroUsize::new(2).unwrap())
+			.queue_order(QueueOrder::Lifo)
+			.coalesce_policy(MergeEverything)
+			.build();
+
+		with_world_commands(&mut world, |commands| {
+			undoredo.push_and_apply(AddToCounter(1), commands)?;
+			undoredo.push_and_apply(AddToCounter(2), commands)?;
+			undoredo.undo(commands)
+		})
+		.unwrap();
+		undoredo.push_to_queue(AddToCounter(3)).unwrap();
+		undoredo.begin_gesture("Drag");
+		undoredo.set_savepoint();
+
+		undoredo.reset();
+		assert_eq!(undoredo.history.iter().count(), 0);
+		assert!(undoredo.queued_operations.is_empty());
+		assert!(!undoredo.is_gesture_active());
+		assert_eq!(undoredo.steps_from_savepoint(), None);
+
+		assert_eq!(undoredo.history.limit, NonZeroUsize::new(2));
+		assert_eq!(undoredo.queue_order(), QueueOrder::Lifo);
+		with_world_commands(&mut world, |commands| {
+			undoredo.push_and_apply(AddToCounter(4), commands)?;
+			undoredo.push_and_apply(AddToCounter(5), commands)
+		})
+		.unwrap();
+		assert_eq!(undoredo.history.iter_committed().count(), 1);
+	}
+
+	/// Adds to [`Counter`], but must be confirmed before being undone.
+	struct Destructive(i32);
+
+	impl Operation for Destructive {
+		fn confirm_undo(&self) -> bool {
+			false
+		}
+
+		fn apply(&mut self, commands: &mut Commands) {
+			AddToCounter(self.0).apply(commands);
+		}
+
+		fn undo(&self, commands: &mut Commands) {
+			AddToCounter(self.0).undo(commands);
+		}
+	}
+
+	#[rstest]
+	fn confirmation_blocks_plain_undo() {
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		let mut undoredo = UndoRedo::new();
+
+		undoredo
+			.push_and_apply_world(Destructive(1), &mut world)
+			.unwrap();
+		undoredo
+			.push_and_apply_world(AddToCounter(2), &mut world)
+			.unwrap();
+		let undone = with_world_commands(&mut world, |commands| undoredo.undo_n(2, commands));
+		assert_eq!(undone, Ok(1));
+		assert!(undoredo.can_undo() && !undoredo.would_undo_succeed());
+
+		assert_eq!(
+			undoredo.undo_world(&mut world),
+			Err(Error::ConfirmationRequired)
+		);
+		assert_eq!(world.resource::<Counter>().0, 1);
+
+		with_world_commands(&mut world, |commands| undoredo.undo_confirmed(commands)).unwrap();
+		assert_eq!(world.resource::<Counter>().0, 0);
+		assert!(!undoredo.can_undo());
+	}
+
+	#[rstest]
+	#[case::batch(false)]
+	#[case::frame_group(true)]
+	fn confirmation_is_required_for_groups_containing_destructive_operations(
+		#[case] by_frame: bool,
+	) {
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		let mut undoredo = UndoRedo::new();
+
+		let mut push_both = |undoredo: &mut UndoRedo| {
+			undoredo
+				.push_and_apply_world(AddToCounter(2), &mut world)
+				.unwrap();
+			undoredo
+				.push_and_apply_world(Destructive(1), &mut world)
+				.unwrap();
+		};
+		if by_frame {
+			undoredo.set_current_frame(Some(1));
+			push_both(&mut undoredo);
+			undoredo.set_current_frame(None);
+		} else {
+			undoredo.batch("Batch", push_both);
+		}
+		assert_eq!(undoredo.history.iter_committed().len(), 1);
+		assert!(undoredo.can_undo() && !undoredo.would_undo_succeed());
+
+		assert_eq!(
+			undoredo.undo_world(&mut world),
+			Err(Error::ConfirmationRequired)
+		);
+		assert_eq!(world.resource::<Counter>().0, 3);
+	}
+
+	#[rstest]
+	fn try_undo_describes_each_outcome() {
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		let mut undoredo = UndoRedo::new();
+		let mut try_undo = |undoredo: &mut UndoRedo| {
+			with_world_commands(&mut world, |commands| undoredo.try_undo(commands))
+		};
+		assert_eq!(try_undo(&mut undoredo), UndoOutcome::NothingToUndo);
+
+		undoredo.register_applied(Destructive(1)).unwrap();
+		undoredo.register_applied(AddToCounter(2)).unwrap();
+		assert_eq!(try_undo(&mut undoredo), UndoOutcome::Performed);
+		assert_eq!(try_undo(&mut undoredo), UndoOutcome::ConfirmationRequired);
+		assert!(undoredo.can_undo());
+	}
+
+	#[rstest]
+	fn operations_are_grouped_by_frame() {
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		let mut undoredo = UndoRedo::new();
+		let mut apply_in_frame = |undoredo: &mut UndoRedo, frame, amounts: &[i32]| {
+			undoredo.set_current_frame(Some(frame));
+			for &amount in amounts {
+				undoredo
+					.push_and_apply_world(AddToCounter(amount), &mut world)
+					.unwrap();
+			}
+		};
+
+		apply_in_frame(&mut undoredo, 1, &[1]);
+		apply_in_frame(&mut undoredo, 2, &[2, 4, 8]);
+		apply_in_frame(&mut undoredo, 3, &[16, 32]);
+		assert_eq!(undoredo.history.iter().count(), 3);
+		assert!(undoredo
+			.history
+			.peek(-3)
+			.unwrap()
+			.operation
+			.is::<AddToCounter>());
+
+		undoredo.undo_world(&mut world).unwrap();
+		assert_eq!(world.resource::<Counter>().0, 15);
+		undoredo.undo_world(&mut world).unwrap();
+		assert_eq!(world.resource::<Counter>().0, 1);
+
+		// Once disabled, operations in the same frame are recorded separately again.
+		undoredo.set_current_frame(None);
+		undoredo
+			.push_and_apply_world(AddToCounter(1), &mut world)
+			.unwrap();
+		undoredo
+			.push_and_apply_world(AddToCounter(1), &mut world)
+			.unwrap();
+		assert_eq!(undoredo.history.iter().count(), 3);
+	}
+
+	#[rstest]
+	fn undo_reason_is_sent_with_the_event() {
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		world.init_resource::<Events<UndoPerformed>>();
+		let mut undoredo = UndoRedo::new();
+		undoredo
+			.push_and_apply_world(AddToCounter(1), &mut world)
+			.unwrap();
+
+		with_world_commands(&mut world, |commands| {
+			undoredo.undo_with_reason("automatic rollback", commands)
+		})
+		.unwrap();
+		let result = with_world_commands(&mut world, |commands| {
+			undoredo.undo_with_reason("user action", commands)
+		});
+		assert_eq!(result, Err(Error::NoApplicableHistory));
+
+		let reasons: Vec<_> = world
+			.resource::<Events<UndoPerformed>>()
+			.iter_current_update_events()
+			.map(|event| event.reason.as_str())
+			.collect();
+		assert_eq!(reasons, ["automatic rollback"]);
+		assert_eq!(world.resource::<Counter>().0, 0);
+	}
+
+	#[rstest]
+	fn undo_all_world_reports_each_step() {
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		world.init_resource::<Events<UndoStep>>();
+		let mut undoredo = UndoRedo::new();
+		for amount in [1, 2, 4] {
+			undoredo
+				.push_and_apply_world(AddToCounter(amount), &mut world)
+				.unwrap();
+		}
+
+		assert_eq!(undoredo.undo_all_world(&mut world), Ok(3));
+		assert_eq!(world.resource::<Counter>().0, 0);
+		let steps: Vec<_> = world
+			.resource::<Events<UndoStep>>()
+			.iter_current_update_events()
+			.map(|step| (step.index, step.remaining))
+			.collect();
+		assert_eq!(steps, [(0, 2), (1, 1), (2, 0)]);
+
+		assert_eq!(
+			undoredo.undo_all_world(&mut world),
+			Err(Error::NoApplicableHistory)
+		);
+	}
+
+	#[rstest]
+	fn tags_can_be_attached_and_read_back() {
+		#[derive(Debug, PartialEq)]
+		struct CreatedBy(&'static str);
+
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		let mut undoredo = UndoRedo::new();
+		assert_eq!(
+			undoredo.tag_last(CreatedBy("brush")),
+			Err(Error::NoApplicableHistory)
+		);
+
+		with_world_commands(&mut world, |commands| {
+			undoredo.push_and_apply(AddToCounter(1), commands)?;
+			undoredo.push_and_apply(AddToCounter(2), commands)
+		})
+		.unwrap();
+		undoredo.tag_last(CreatedBy("brush")).unwrap();
+
+		assert_eq!(undoredo.tag_of::<CreatedBy>(0), None);
+		assert_eq!(undoredo.tag_of(1), Some(&CreatedBy("brush")));
+		assert_eq!(undoredo.tag_of::<u32>(1), None);
+
+		with_world_commands(&mut world, |commands| undoredo.undo(commands)).unwrap();
+		assert_eq!(undoredo.tag_of(1), Some(&CreatedBy("brush")));
+	}
+
+	#[rstest]
+	fn sources_are_recorded_and_can_be_filtered() {
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		let mut undoredo = UndoRedo::new();
+
+		undoredo
+			.push_to_queue_from(AddToCounter(1), OperationSource::System)
+			.unwrap();
+		undoredo.push_to_queue(Nudge(2)).unwrap();
+		undoredo
+			.push_to_queue_from(AddToCounter(4), OperationSource::Script)
+			.unwrap();
+		undoredo
+			.push_to_queue_from(Nudge(8), OperationSource::System)
+			.unwrap();
+		with_world_commands(&mut world, |commands| {
+			undoredo.apply_queue(commands)?;
+			// Merged into the previous `Nudge`, which keeps its source.
+			undoredo.push_and_apply(Nudge(16), commands)?;
+			undoredo.push_and_apply(AddToCounter(32), commands)
+		})
+		.unwrap();
+		assert_eq!(world.resource::<Counter>().0, 63);
+
+		let sources: Vec<_> = undoredo
+			.iter_details_with_source()
+			.map(|(_, source)| source)
+			.collect();
+		assert_eq!(
+			sources,
+			[
+				OperationSource::System,
+				OperationSource::User,
+				OperationSource::Script,
+				OperationSource::System,
+				OperationSource::User,
+			]
+		);
+
+		let from_systems: Vec<_> = undoredo
+			.iter_details_with_source()
+			.filter(|(_, source)| *source == OperationSource::System)
+			.map(|(details, _)| details.name)
+			.collect();
+		assert_eq!(from_systems, ["", "Nudge"]);
+
+		with_world_commands(&mut world, |commands| undoredo.undo(commands)).unwrap();
+		assert_eq!(undoredo.source_of(3), Some(OperationSource::System));
+		assert_eq!(undoredo.source_of(5), None);
+	}
+
+	#[rstest]
+	fn warn_threshold_reports_crossing_once() {
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		let mut undoredo = UndoRedo::new();
+		undoredo.set_warn_threshold(NonZeroUsize::new(2));
+
+		let crossings = Arc::new(Mutex::new(vec![]));
+		let crossings_by_hook = Arc::clone(&crossings);
+		undoredo.set_on_warn_threshold(Box::new(move |len| {
+			crossings_by_hook.lock().unwrap().push(len);
+		}));
+
+		with_world_commands(&mut world, |commands| {
+			for amount in 1..=4 {
+				undoredo.push_and_apply(AddToCounter(amount), commands)?;
+			}
+			Ok::<_, Error>(())
+		})
+		.unwrap();
+		assert_eq!(*crossings.lock().unwrap(), [2]);
+	}
+
+	#[rstest]
+	fn on_evict_reports_dropped_operation_names() {
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+
+		let mut history = History::new();
+		history.limit = NonZeroUsize::new(1);
+		let mut undoredo = UndoRedo::with_history(history);
+
+		let evicted = Arc::new(Mutex::new(vec![]));
+		let evicted_by_hook = Arc::clone(&evicted);
+		undoredo.set_on_evict(Box::new(move |details: &Details| {
+			evicted_by_hook.lock().unwrap().push(details.name.clone());
+		}));
+
+		with_world_commands(&mut world, |commands| {
+			let move_object = OperationGroup::new(Details {
+				name: "Move Object".to_owned(),
+			});
+			undoredo.push_and_apply(move_object, commands)?;
+			undoredo.push_and_apply(AddToCounter(1), commands)
+		})
+		.unwrap();
+		assert_eq!(*evicted.lock().unwrap(), ["Move Object"]);
+	}
+
+	/// Merges every pair of operations into an [`OperationGroup`].
+	struct MergeEverything;
+
+	impl CoalescePolicy for MergeEverything {
+		fn coalesce(
+			&self,
+			previous: Box<dyn Operation>,
+			next: Box<dyn Operation>,
+		) -> CoalesceResult {
+			let mut group = OperationGroup::new(previous.details());
+			group.push_boxed(previous);
+			group.push_boxed(next);
+			Ok(Box::new(group))
+		}
+	}
+
+	#[rstest]
+	fn builder_applies_each_setting() {
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		let mut undoredo = UndoRedoBuilder::new()
+			.limit(NonZeroUsize::new(2).unwrap())
+			.warn_threshold(NonZeroUsize::new(1).unwrap())
+			.queue_order(QueueOrder::Lifo)
+			.coalesce_policy(MergeEverything)
+			.build_recording();
+
+		assert_eq!(undoredo.history.limit, NonZeroUsize::new(2));
+		assert_eq!(undoredo.history.warn_threshold, NonZeroUsize::new(1));
+		assert_eq!(undoredo.queue_order(), QueueOrder::Lifo);
+		assert_eq!(undoredo.history_mode(), HistoryMode::Recording);
+
+		with_world_commands(&mut world, |commands| {
+			undoredo.push_and_apply(AddToCounter(1), commands)?;
+			undoredo.push_and_apply(AddToCounter(2), commands)
+		})
+		.unwrap();
+		assert_eq!(undoredo.history.iter_committed().count(), 1);
+		assert_eq!(undoredo.recorded_events().len(), 2);
+
+		let mut undoredo = UndoRedoBuilder::new()
+			.history_mode(HistoryMode::Disabled)
+			.build();
+		assert_eq!(undoredo.history_mode(), HistoryMode::Disabled);
+		with_world_commands(&mut world, |commands| {
+			undoredo.push_and_apply(AddToCounter(4), commands)
+		})
+		.unwrap();
+		assert_eq!(world.resource::<Counter>().0, 7);
+		assert!(!undoredo.can_undo());
+	}
+
+	#[rstest]
+	fn coalesce_policy_merges_unrelated_operations() {
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		let mut undoredo = UndoRedo::new();
+		undoredo.set_coalesce_policy(MergeEverything);
+
+		undoredo.push_to_queue(SpawnEntity::default()).unwrap();
+		undoredo.push_to_queue(AddToCounter(2)).unwrap();
+		undoredo.apply_queue_world(&mut world).unwrap();
+		assert_eq!(undoredo.history.iter().count(), 1);
+
+		undoredo.undo_world(&mut world).unwrap();
+		assert_eq!(world.resource::<Counter>().0, 0);
+		assert_eq!(world.entities().len(), 0);
+	}
+
+	/// Adds to [`Counter`], merging with consecutive `Nudge`s.
+	struct Nudge(i32);
+
+	impl Operation for Nudge {
+		fn apply(&mut self, commands: &mut Commands) {
+			AddToCounter(self.0).apply(commands);
+		}
+
+		fn undo(&self, commands: &mut Commands) {
+			AddToCounter(self.0).undo(commands);
+		}
+
+		fn merge_into(self: Box<Self>, next: Box<dyn Operation>) -> CoalesceResult {
+			match next.downcast::<Self>() {
+				Ok(next) => Ok(Box::new(Self(self.0 + next.0))),
+				Err(next) => Err((self, next)),
+			}
+		}
+
+		fn inverts(&self, other: &dyn Operation) -> bool {
+			other
+				.downcast_ref::<Self>()
+				.is_some_and(|other| other.0 == -self.0)
+		}
+	}
+
+	#[rstest]
+	fn disabled_history_applies_without_recording() {
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		let mut undoredo = UndoRedo::new();
+
+		with_world_commands(&mut world, |commands| {
+			undoredo.push_and_apply(AddToCounter(1), commands)?;
+			undoredo.push_and_apply(AddToCounter(2), commands)?;
+			undoredo.undo(commands)
+		})
+		.unwrap();
+		assert!(undoredo.can_redo());
+
+		undoredo.set_history_mode(HistoryMode::Disabled);
+		with_world_commands(&mut world, |commands| {
+			undoredo.push_and_apply(AddToCounter(4), commands)?;
+			undoredo.push_and_apply(AddToCounter(8), commands)
+		})
+		.unwrap();
+		assert_eq!(world.resource::<Counter>().0, 13);
+		assert_eq!(undoredo.history.iter_committed().count(), 1);
+		assert!(!undoredo.can_redo());
+	}
+
+	#[rstest]
+	#[case::merge_into(false)]
+	#[case::frame_grouping(true)]
+	fn disabled_history_does_not_merge_into_recorded_entries(#[case] group_by_frame: bool) {
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		let mut undoredo = UndoRedo::new();
+		undoredo.set_cancel_inverses(true);
+		if group_by_frame {
+			undoredo.set_current_frame(Some(0));
+		}
+
+		with_world_commands(&mut world, |commands| {
+			undoredo.push_and_apply(Nudge(1), commands)?;
+			undoredo.set_history_mode(HistoryMode::Disabled);
+			undoredo.push_and_apply(Nudge(2), commands)?;
+			undoredo.push_and_apply(Nudge(-1), commands)
+		})
+		.unwrap();
+		assert_eq!(world.resource::<Counter>().0, 2);
+		assert_eq!(undoredo.history.iter_committed().count(), 1);
+
+		// Undoing only reverts the recorded operation.
+		undoredo.undo_world(&mut world).unwrap();
+		assert_eq!(world.resource::<Counter>().0, 1);
+	}
+
+	/// An operation which does nothing, but reports a given weight.
+	struct Weighted(usize);
+
+	impl Operation for Weighted {
+		fn weight(&self) -> usize {
+			self.0
+		}
+
+		fn apply(&mut self, _commands: &mut Commands) {}
+
+		fn undo(&self, _commands: &mut Commands) {}
+	}
+
+	#[rstest]
+	fn next_weights_follow_the_cursor() {
+		let mut world = World::new();
+		let mut undoredo = UndoRedo::new();
+		assert_eq!(undoredo.next_undo_weight(), None);
+
+		with_world_commands(&mut world, |commands| {
+			undoredo.push_and_apply(Weighted(10), commands)?;
+			undoredo.push_and_apply(Weighted(20), commands)?;
+			undoredo.push_and_apply(Weighted(30), commands)?;
+			undoredo.undo(commands)
+		})
+		.unwrap();
+		assert_eq!(undoredo.next_undo_weight(), Some(20));
+		assert_eq!(undoredo.next_redo_weight(), Some(30));
+
+		undoredo.redo_world(&mut world).unwrap();
+		assert_eq!(undoredo.next_undo_weight(), Some(30));
+		assert_eq!(undoredo.next_redo_weight(), None);
+	}
+
+	#[rstest]
+	fn steps_from_savepoint_follow_the_cursor() {
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		let mut undoredo = UndoRedo::new();
+		assert_eq!(undoredo.steps_from_savepoint(), None);
+
+		undoredo
+			.push_and_apply_world(AddToCounter(1), &mut world)
+			.unwrap();
+		undoredo.set_savepoint();
+		assert_eq!(undoredo.steps_from_savepoint(), Some(0));
+
+		undoredo
+			.push_and_apply_world(AddToCounter(1), &mut world)
+			.unwrap();
+		undoredo
+			.push_and_apply_world(AddToCounter(1), &mut world)
+			.unwrap();
+		assert_eq!(undoredo.steps_from_savepoint(), Some(-2));
+
+		for _ in 0..3 {
+			undoredo.undo_world(&mut world).unwrap();
+		}
+		assert_eq!(undoredo.steps_from_savepoint(), Some(1));
+
+		undoredo.redo_world(&mut world).unwrap();
+		assert_eq!(undoredo.steps_from_savepoint(), Some(0));
+
+		undoredo.clear_savepoint();
+		assert_eq!(undoredo.steps_from_savepoint(), None);
+	}
+
+	#[rstest]
+	fn savepoint_at_start_survives_new_operations() {
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		let mut undoredo = UndoRedo::new();
+		undoredo.set_savepoint();
+		assert_eq!(undoredo.steps_from_savepoint(), Some(0));
+
+		undoredo
+			.push_and_apply_world(AddToCounter(1), &mut world)
+			.unwrap();
+		undoredo.undo_world(&mut world).unwrap();
+		undoredo
+			.push_and_apply_world(AddToCounter(2), &mut world)
+			.unwrap();
+		assert_eq!(undoredo.steps_from_savepoint(), Some(-1));
+	}
+
+	#[rstest]
+	#[case::trimmed_by_limit(false)]
+	#[case::replaced_after_undo(true)]
+	fn savepoint_is_invalidated_when_unreachable(#[case] undo_first: bool) {
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		let mut undoredo = UndoRedo::new();
+		undoredo.history.limit = NonZeroUsize::new(2);
+
+		undoredo
+			.push_and_apply_world(AddToCounter(1), &mut world)
+			.unwrap();
+		undoredo
+			.push_and_apply_world(AddToCounter(1), &mut world)
+			.unwrap();
+		undoredo.set_savepoint();
+		if undo_first {
+			undoredo.undo_world(&mut world).unwrap();
+			undoredo.undo_world(&mut world).unwrap();
+			assert_eq!(undoredo.steps_from_savepoint(), Some(2));
+		} else {
+			undoredo
+				.push_and_apply_world(AddToCounter(1), &mut world)
+				.unwrap();
+			assert_eq!(undoredo.steps_from_savepoint(), Some(-1));
+		}
+
+		undoredo
+			.push_and_apply_world(AddToCounter(1), &mut world)
+			.unwrap();
+		assert_eq!(undoredo.steps_from_savepoint(), None);
+	}
+
+	#[rstest]
+	fn push_no_coalesce_breaks_a_merge_run() {
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		let mut undoredo = UndoRedo::new();
+		undoredo.set_cancel_inverses(true);
+
+		with_world_commands(&mut world, |commands| {
+			undoredo.push_and_apply(Nudge(1), commands)?;
+			undoredo.push_and_apply(Nudge(2), commands)?;
+			undoredo.push_no_coalesce(Nudge(-3), commands)?;
+			undoredo.push_and_apply(Nudge(4), commands)?;
+			undoredo.push_and_apply(Nudge(5), commands)
+		})
+		.unwrap();
+		assert_eq!(world.resource::<Counter>().0, 9);
+		let amounts: Vec<_> = undoredo
+			.history
+			.iter()
+			.filter_map(|entry| entry.operation.downcast_ref::<Nudge>())
+			.map(|nudge| nudge.0)
+			.collect();
+		assert_eq!(amounts, [3, -3, 9]);
+	}
+
+	#[rstest]
+	fn merging_into_the_savepoint_invalidates_it() {
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		let mut undoredo = UndoRedo::new();
+
+		undoredo.push_and_apply_world(Nudge(1), &mut world).unwrap();
+		undoredo.set_savepoint();
+		undoredo.push_and_apply_world(Nudge(1), &mut world).unwrap();
+		assert_eq!(undoredo.steps_from_savepoint(), None);
+	}
+
+	#[rstest]
+	#[case::enabled(true, 0)]
+	#[case::disabled(false, 1)]
+	fn inverse_operations_cancel_out(#[case] enabled: bool, #[case] expected_len: usize) {
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		let mut undoredo = UndoRedo::new();
+		undoredo.set_cancel_inverses(enabled);
+
+		with_world_commands(&mut world, |commands| {
+			undoredo.push_and_apply(Nudge(3), commands)?;
+			undoredo.push_and_apply(Nudge(-3), commands)
+		})
+		.unwrap();
+		assert_eq!(world.resource::<Counter>().0, 0);
+		assert_eq!(undoredo.history.iter().count(), expected_len);
+		assert_eq!(undoredo.can_undo(), !enabled);
+	}
+
+	#[rstest]
+	fn iter_details_collapses_or_expands_groups() {
+		let named = |name: &str| {
+			OperationGroup::new(Details {
+				name: name.to_owned(),
+			})
+		};
+		let mut inner = named("Inner");
+		inner.push(named("Leaf"));
+		let mut outer = named("Outer");
+		outer.push(inner);
+		outer.push(named("Sibling"));
+
+		let mut world = World::new();
+		let mut undoredo = UndoRedo::new();
+		with_world_commands(&mut world, |commands| {
+			undoredo.push_and_apply(named("First"), commands)?;
+			undoredo.push_and_apply(outer, commands)?;
+			undoredo.push_and_apply(named("Last"), commands)?;
+			undoredo.undo(commands)
+		})
+		.unwrap();
+
+		crate::assert_history_names!(undoredo, ["First", "Outer", "Last"]);
+
+		let expanded: Vec<_> = undoredo
+			.iter_details_expanded()
+			.map(|(depth, details)| (depth, details.name))
+			.collect();
+		assert_eq!(
+			expanded,
+			[
+				(0, "First".to_owned()),
+				(0, "Outer".to_owned()),
+				(1, "Inner".to_owned()),
+				(2, "Leaf".to_owned()),
+				(1, "Sibling".to_owned()),
+				(0, "Last".to_owned()),
+			]
+		);
+	}
+
+	#[rstest]
+	fn operation_runs_through_schedule() {
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		world.init_resource::<UndoRedo>();
+
+		let mut schedule = Schedule::default();
+		schedule.add_systems(run_operation_as_system::<SpawnEntity>);
+		schedule.run(&mut world);
+		schedule.run(&mut world);
+		assert_eq!(world.entities().len(), 2);
+
+		world.resource_scope(|world, mut undoredo: Mut<UndoRedo>| {
+			undoredo.undo_world(world).unwrap();
+		});
+		assert_eq!(world.entities().len(), 1);
+	}
+
+	#[derive(Component)]
+	struct Health(i32);
+
+	/// Sets an entity's [`Health`], reading the previous value from the World when applied.
+	struct SetHealth {
+		entity: Entity,
+		value: i32,
+		previous: Option<i32>,
+	}
+
+	impl Operation for SetHealth {
+		fn apply(&mut self, _commands: &mut Commands) {
+			unreachable!("only applied through `apply_world` in these tests");
+		}
+
+		fn apply_world(&mut self, world: &mut World) -> Result<(), Error> {
+			let mut health = world
+				.get_mut::<Health>(self.entity)
+				.ok_or_else(|| Error::OperationFailed("entity has no health".into()))?;
+			self.previous = Some(health.0);
+			health.0 = self.value;
+			Ok(())
+		}
+
+		fn undo(&self, commands: &mut Commands) {
+			if let Some(previous) = self.previous {
+				commands.entity(self.entity).insert(Health(previous));
+			}
+		}
+	}
+
+	/// Does nothing, but fails if it's applied a second time.
+	struct FailsWhenRedone(bool);
+
+	impl Operation for FailsWhenRedone {
+		fn apply(&mut self, _commands: &mut Commands) {}
+
+		fn apply_in_context(
+			&mut self,
+			_commands: &mut Commands,
+			_context: &mut OperationContext,
+		) -> Result<(), Error> {
+			if self.0 {
+				return Err(Error::OperationFailed("already applied".into()));
+			}
+			self.0 = true;
+			Ok(())
+		}
+
+		fn undo(&self, _commands: &mut Commands) {}
+	}
+
+	#[rstest]
+	fn failed_redo_at_the_limit_keeps_the_oldest_operation() {
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		let evicted = Arc::new(Mutex::new(vec![]));
+		let mut undoredo = UndoRedo::new();
+		undoredo.history.limit = NonZeroUsize::new(3);
+		let evicted_by_hook = Arc::clone(&evicted);
+		undoredo.set_on_evict(Box::new(move |details: &Details| {
+			evicted_by_hook.lock().unwrap().push(details.name.clone());
+		}));
+
+		with_world_commands(&mut world, |commands| {
+			undoredo.push_and_apply(Nudge(1), commands)?;
+			undoredo.push_and_apply(AddToCounter(2), commands)?;
+			undoredo.push_and_apply(FailsWhenRedone(false), commands)?;
+			undoredo.undo(commands)
+		})
+		.unwrap();
+		undoredo.history.set_limit(NonZeroUsize::new(2));
+
+		let result = undoredo.redo_world(&mut world);
+		assert!(matches!(result, Err(Error::OperationFailed(_))));
+		assert!(evicted.lock().unwrap().is_empty());
+		crate::assert_history_names!(undoredo, ["Nudge", "", "FailsWhenRedone"]);
+		assert_eq!(undoredo.history.split_index(), 2);
+	}
+
+	#[rstest]
+	fn failed_group_redo_leaves_no_members_applied() {
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		let mut undoredo = UndoRedo::new();
+
+		let mut group = OperationGroup::default();
+		group.push(AddToCounter(1));
+		group.push(FailsWhenRedone(false));
+		undoredo.push_and_apply_world(group, &mut world).unwrap();
+		undoredo.undo_world(&mut world).unwrap();
+		assert_eq!(world.resource::<Counter>().0, 0);
+
+		for _ in 0..2 {
+			let result = undoredo.redo_world(&mut world);
+			assert!(matches!(result, Err(Error::OperationFailed(_))));
+			assert_eq!(world.resource::<Counter>().0, 0);
+		}
+		assert!(undoredo.can_redo());
+	}
+
+	#[rstest]
+	fn apply_world_can_read_undo_data() {
+		let mut world = World::new();
+		let entity = world.spawn(Health(10)).id();
+		let mut undoredo = UndoRedo::new();
+
+		undoredo
+			.push_and_apply_world(
+				SetHealth {
+					entity,
+					value: 3,
+					previous: None,
+				},
+				&mut world,
+			)
+			.unwrap();
+		assert_eq!(world.get::<Health>(entity).unwrap().0, 3);
+
+		undoredo.undo_world(&mut world).unwrap();
+		assert_eq!(world.get::<Health>(entity).unwrap().0, 10);
+
+		let result = undoredo.push_and_apply_world(
+			SetHealth {
+				entity: world.spawn_empty().id(),
+				value: 3,
+				previous: None,
+			},
+			&mut world,
+		);
+		assert!(matches!(result, Err(Error::OperationFailed(_))));
+		assert!(!undoredo.can_undo());
+	}
+
+	#[rstest]
+	fn register_applied_can_be_undone() {
+		let mut world = World::new();
+		world.insert_resource(Counter(5));
+		let mut undoredo = UndoRedo::new();
+
+		undoredo.register_applied(AddToCounter(5)).unwrap();
+		assert_eq!(world.resource::<Counter>().0, 5);
+
+		undoredo.undo_world(&mut world).unwrap();
+		assert_eq!(world.resource::<Counter>().0, 0);
+		undoredo.redo_world(&mut world).unwrap();
+		assert_eq!(world.resource::<Counter>().0, 5);
+	}
+
+	#[rstest]
+	fn gesture_applies_live_and_records_one_entry() {
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		let mut undoredo = UndoRedo::new();
+
+		undoredo.begin_gesture("Drag");
+		for amount in [1, 2, 3] {
+			with_world_commands(&mut world, |commands| {
+				undoredo.update_gesture(AddToCounter(amount), commands)
+			})
+			.unwrap();
+		}
+		assert_eq!(world.resource::<Counter>().0, 6);
+		assert!(!undoredo.can_undo());
+
+		undoredo.end_gesture().unwrap();
+		assert!(!undoredo.is_gesture_active());
+		crate::assert_history_names!(undoredo, ["Drag"]);
+
+		undoredo.undo_world(&mut world).unwrap();
+		assert_eq!(world.resource::<Counter>().0, 0);
+		assert_eq!(undoredo.end_gesture(), Err(Error::NoActiveGesture));
+	}
+
+	#[rstest]
+	fn batch_records_one_entry() {
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		let mut undoredo = UndoRedo::new();
+
+		with_world_commands(&mut world, |commands| {
+			undoredo.push_and_apply(AddToCounter(1), commands)?;
+			undoredo.batch("Batch", |undoredo| {
+				undoredo.push_and_apply(Nudge(2), commands)?;
+				undoredo.push_and_apply(Nudge(4), commands)?;
+				undoredo.batch("Inner", |undoredo| {
+					undoredo.register_applied(AddToCounter(0))
+				})
+			})
+		})
+		.unwrap();
+		assert_eq!(world.resource::<Counter>().0, 7);
+		crate::assert_history_names!(undoredo, ["", "Batch"]);
+
+		undoredo.undo_world(&mut world).unwrap();
+		assert_eq!(world.resource::<Counter>().0, 1);
+		undoredo.redo_world(&mut world).unwrap();
+		assert_eq!(world.resource::<Counter>().0, 7);
+
+		undoredo.batch("Empty", |_| {});
+		crate::assert_history_names!(undoredo, ["", "Batch"]);
+	}
+
+	#[rstest]
+	fn operations_merge_themselves_without_a_policy() {
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		let mut undoredo = UndoRedo::new();
+
+		with_world_commands(&mut world, |commands| {
+			undoredo.push_and_apply(Nudge(1), commands)?;
+			undoredo.push_and_apply(Nudge(2), commands)?;
+			undoredo.push_and_apply(AddToCounter(4), commands)?;
+			undoredo.push_and_apply(Nudge(8), commands)
+		})
+		.unwrap();
+		assert_eq!(world.resource::<Counter>().0, 15);
+		assert_eq!(undoredo.history.iter_committed().count(), 3);
+
+		undoredo.undo_world(&mut world).unwrap();
+		assert_eq!(world.resource::<Counter>().0, 7);
+		undoredo.undo_world(&mut world).unwrap();
+		assert_eq!(world.resource::<Counter>().0, 3);
+		undoredo.undo_world(&mut world).unwrap();
+		assert_eq!(world.resource::<Counter>().0, 0);
+	}
+
+	#[rstest]
+	#[case::individual(QueueFlushMode::Individual, 3)]
+	#[case::grouped(QueueFlushMode::Grouped, 0)]
+	fn apply_queued_operations_flush_mode(
+		#[case] flush_mode: QueueFlushMode,
+		#[case] expected_after_undo: i32,
+	) {
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		world.insert_resource(flush_mode);
+
+		let mut undoredo = UndoRedo::new();
+		undoredo.push_to_queue(AddToCounter(3)).unwrap();
+		undoredo.push_to_queue(AddToCounter(4)).unwrap();
+		world.insert_resource(undoredo);
+
+		world.run_system_once(apply_queued_operations).unwrap();
+		assert_eq!(world.resource::<Counter>().0, 7);
+
+		world
+			.resource_scope(|world, mut undoredo: Mut<UndoRedo>| undoredo.undo_world(world))
+			.unwrap();
+		assert_eq!(world.resource::<Counter>().0, expected_after_undo);
+	}
+
+	#[rstest]
+	#[case::fifo(QueueOrder::Fifo, ["a", "b", "c"])]
+	#[case::lifo(QueueOrder::Lifo, ["c", "b", "a"])]
+	fn apply_queue_and_notify_sends_an_event_per_operation(
+		#[case] order: QueueOrder,
+		#[case] expected: [&str; 3],
+	) {
+		let mut world = World::new();
+		world.init_resource::<Events<OperationApplied>>();
+
+		let mut undoredo = UndoRedo::new();
+		undoredo.set_queue_order(order);
+		for name in ["a", "b", "c"] {
+			let group = OperationGroup::new(Details {
+				name: name.to_owned(),
+			});
+			undoredo.push_to_queue(group).unwrap();
+		}
+		world.insert_resource(undoredo);
+
+		world.run_system_once(apply_queue_and_notify).unwrap();
+		let names: Vec<_> = world
+			.resource::<Events<OperationApplied>>()
+			.iter_current_update_events()
+			.map(|event| event.details.name.as_str())
+			.collect();
+		assert_eq!(names, expected);
+		assert_eq!(
+			world.resource::<UndoRedo>().history.iter_committed().len(),
+			3
+		);
+
+		world.run_system_once(apply_queue_and_notify).unwrap();
+		let events = world.resource::<Events<OperationApplied>>();
+		assert_eq!(events.iter_current_update_events().count(), 3);
+	}
+
+	#[rstest]
+	fn recording_replays_to_same_state() {
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		let mut recording = RecordingUndoRedo::default();
+
+		with_world_commands(&mut world, |commands| {
+			recording.push_and_apply(AddToCounter(1), commands)?;
+			recording.push_to_queue(AddToCounter(10))?;
+			recording.push_to_queue(AddToCounter(100))?;
+			recording.apply_queue(commands)?;
+			recording.undo(commands)?;
+			recording.undo(commands)?;
+			recording.redo(commands)
+		})
+		.unwrap();
+		assert_eq!(recording.recorded_events().len(), 6);
+
+		// Our test operation's details don't say how much they add, so replay them in order.
+		let mut amounts = [1, 10, 100].into_iter();
+		let mut replay_world = World::new();
+		replay_world.init_resource::<Counter>();
+		let replayed = RecordingUndoRedo::replay(
+			recording.recorded_events(),
+			UndoRedo::new(),
+			&mut replay_world,
+			|_| Box::new(AddToCounter(amounts.next().unwrap())),
+		)
+		.unwrap();
+
+		assert_eq!(world.resource::<Counter>().0, 11);
+		assert_eq!(replay_world.resource::<Counter>().0, 11);
+		assert_eq!(replayed.recorded_events(), recording.recorded_events());
+	}
+
+	#[rstest]
+	fn recording_replays_locality_groups_as_one_entry() {
+		/// Adds to [`Counter`], in a locality shared with every other `InChunk`.
+		struct InChunk(i32);
+
+		impl Operation for InChunk {
+			fn locality_key(&self) -> Option<u64> {
+				Some(0)
+			}
+
+			fn apply(&mut self, commands: &mut Commands) {
+				AddToCounter(self.0).apply(commands);
+			}
+
+			fn undo(&self, commands: &mut Commands) {
+				AddToCounter(self.0).undo(commands);
+			}
+		}
+
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		let mut recording = UndoRedoBuilder::new()
+			.limit(NonZeroUsize::new(4).unwrap())
+			.build_recording();
+
+		with_world_commands(&mut world, |commands| {
+			recording.push_and_apply(AddToCounter(1), commands)?;
+			for amount in [10, 100, 1000] {
+				recording.push_to_queue(InChunk(amount))?;
+			}
+			recording.apply_queue(commands)?;
+			recording.undo(commands)
+		})
+		.unwrap();
+		assert_eq!(world.resource::<Counter>().0, 1);
+		assert_eq!(recording.recorded_events().len(), 3);
+
+		// The group is recreated as a whole, as it was committed as one entry.
+		let mut group = OperationGroup::new(InChunk(10).details());
+		for amount in [10, 100, 1000] {
+			group.push(InChunk(amount));
+		}
+		let mut operations = [
+			Box::new(AddToCounter(1)) as Box<dyn Operation>,
+			Box::new(group),
+		]
+		.into_iter();
+		let mut replay_world = World::new();
+		replay_world.init_resource::<Counter>();
+		let replayed = RecordingUndoRedo::replay(
+			recording.recorded_events(),
+			UndoRedoBuilder::new()
+				.limit(NonZeroUsize::new(4).unwrap())
+				.build(),
+			&mut replay_world,
+			|_| operations.next().unwrap(),
+		)
+		.unwrap();
+
+		assert_eq!(replay_world.resource::<Counter>().0, 1);
+		assert_eq!(replayed.history.iter_committed().len(), 1);
+		assert_eq!(replayed.history.iter_undone().count(), 1);
+		assert_eq!(replayed.recorded_events(), recording.recorded_events());
+	}
+
+	/// An operation which always fails validation.
+	struct AlwaysInvalid;
+
+	impl Operation for AlwaysInvalid {
+		fn validate(&self) -> Result<(), String> {
+			Err("always invalid".into())
+		}
+
+		fn apply(&mut self, _commands: &mut Commands) {}
+
+		fn undo(&self, _commands: &mut Commands) {}
+	}
+
+	#[rstest]
+	fn invalid_operations_are_rejected() {
+		let mut world = World::new();
+		let mut undoredo = UndoRedo::new();
+
+		let result = undoredo.push_to_queue(AlwaysInvalid);
+		assert!(matches!(result, Err(Error::InvalidOperation(_))));
+		assert!(undoredo.queued_operations.is_empty());
+
+		let result = with_world_commands(&mut world, |commands| {
+			undoredo.push_and_apply(AlwaysInvalid, commands)
+		});
+		assert!(matches!(result, Err(Error::InvalidOperation(_))));
+		assert_eq!(undoredo.history.iter().count(), 0);
+	}
+
+	/// Wraps an operation, giving it a transaction ID.
+	struct InTransaction<O>(u64, O);
+
+	impl<O: Operation> Operation for InTransaction<O> {
+		fn transaction_id(&self) -> Option<u64> {
+			Some(self.0)
+		}
+
+		fn apply(&mut self, commands: &mut Commands) {
+			self.1.apply(commands);
+		}
+
+		fn undo(&self, commands: &mut Commands) {
+			self.1.undo(commands);
+		}
+	}
+
+	/// Adds to [`Counter`], reporting the given name, affected entity, and transaction ID.
+	struct Keyed(&'static str, u32, Option<u64>, i32);
+
+	impl Operation for Keyed {
+		fn details(&self) -> Details {
+			Details {
+				name: self.0.to_owned(),
+			}
+		}
+
+		fn affected_entities(&self) -> Vec<Entity> {
+			vec![Entity::from_raw(self.1)]
+		}
+
+		fn transaction_id(&self) -> Option<u64> {
+			self.2
+		}
+
+		fn apply(&mut self, commands: &mut Commands) {
+			AddToCounter(self.3).apply(commands);
+		}
+
+		fn undo(&self, commands: &mut Commands) {
+			AddToCounter(self.3).undo(commands);
+		}
+	}
+
+	#[rstest]
+	#[case::none(CoalesceKey::None, &[1, 2, 4, 8, 16])]
+	#[case::same_name(CoalesceKey::SameName, &[3, 12, 16])]
+	#[case::same_entity(CoalesceKey::SameEntity, &[7, 24])]
+	#[case::same_transaction_id(CoalesceKey::SameTransactionId, &[1, 14, 16])]
+	fn coalesce_by_groups_matching_operations(
+		#[case] key: CoalesceKey,
+		#[case] entry_amounts: &[i32],
+	) {
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		let mut undoredo = UndoRedo::new();
+		undoredo.set_coalesce_by(key);
+
+		with_world_commands(&mut world, |commands| {
+			undoredo.push_and_apply(Keyed("Move", 1, Some(1), 1), commands)?;
+			undoredo.push_and_apply(Keyed("Move", 1, Some(2), 2), commands)?;
+			undoredo.push_and_apply(Keyed("Rotate", 1, Some(2), 4), commands)?;
+			undoredo.push_and_apply(Keyed("Rotate", 2, Some(2), 8), commands)?;
+			undoredo.push_and_apply(Keyed("Move", 2, None, 16), commands)
+		})
+		.unwrap();
+		assert_eq!(world.resource::<Counter>().0, 31);
+		assert_eq!(undoredo.history.iter_committed().len(), entry_amounts.len());
+
+		for amount in entry_amounts.iter().rev() {
+			let before = world.resource::<Counter>().0;
+			undoredo.undo_world(&mut world).unwrap();
+			assert_eq!(before - world.resource::<Counter>().0, *amount);
+		}
+	}
+
+	#[rstest]
+	fn undo_last_action_undoes_whole_transaction() {
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		let mut undoredo = UndoRedo::new();
+		undoredo.push_to_queue(AddToCounter(1)).unwrap();
+		undoredo
+			.push_to_queue(InTransaction(7, AddToCounter(10)))
+			.unwrap();
+		undoredo
+			.push_to_queue(InTransaction(8, AddToCounter(100)))
+			.unwrap();
+		undoredo
+			.push_to_queue(InTransaction(8, AddToCounter(1000)))
+			.unwrap();
+		undoredo.apply_queue_world(&mut world).unwrap();
+
+		let mut undo_last_action = |world: &mut World| {
+			with_world_commands(world, |commands| undoredo.undo_last_action(commands)).unwrap();
+			world.resource::<Counter>().0
+		};
+		assert_eq!(undo_last_action(&mut world), 11);
+		assert_eq!(undo_last_action(&mut world), 1);
+		assert_eq!(undo_last_action(&mut world), 0);
+	}
+
+	#[rstest]
+	fn apply_queue_limited_leaves_remainder_queued() {
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		let mut undoredo = UndoRedo::new();
+		for _ in 0..5 {
+			undoredo.push_to_queue(AddToCounter(1)).unwrap();
+		}
+
+		let mut apply_three = |world: &mut World| {
+			with_world_commands(world, |commands| undoredo.apply_queue_limited(3, commands))
+		};
+		assert_eq!(apply_three(&mut world).unwrap(), 3);
+		assert_eq!(world.resource::<Counter>().0, 3);
+		assert_eq!(apply_three(&mut world).unwrap(), 2);
+		assert_eq!(world.resource::<Counter>().0, 5);
+		assert_eq!(apply_three(&mut world), Err(Error::NoQueuedOperations));
+	}
+
+	#[rstest]
+	fn world_methods_apply_immediately() {
+		let mut world = World::new();
+		world.init_resource::<Counter>();
+		let mut undoredo = UndoRedo::new();
+		undoredo.push_to_queue(AddToCounter(5)).unwrap();
+
+		undoredo.apply_queue_world(&mut world).unwrap();
+		assert_eq!(world.resource::<Counter>().0, 5);
+		undoredo.undo_world(&mut world).unwrap();
+		assert_eq!(world.resource::<Counter>().0, 0);
+		undoredo.redo_world(&mut world).unwrap();
+		assert_eq!(world.resource::<Counter>().0, 5);
+	}
+
+	#[rstest]
+	fn merge_interleaves_by_sequence() {
+		let mut world = World::new();
+		let mut first = UndoRedo::default();
+		let mut second = UndoRedo::default();
+
+		with_world_commands(&mut world, |commands| {
+			for _ in 0..3 {
+				first.push_and_apply(SpawnEntity::default(), commands)?;
+				second.push_and_apply(SpawnEntity::default(), commands)?;
+			}
+			Ok::<_, Error>(())
+		})
+		.unwrap();
+
+		let mut expected: Vec<u64> = (0..3)
+			.filter_map(|i| first.sequence_of(i))
+			.chain((0..3).filter_map(|i| second.sequence_of(i)))
+			.collect();
+		expected.sort_unstable();
+
+		first.merge(second);
+
+		let merged: Vec<u64> = (0..6).filter_map(|i| first.sequence_of(i)).collect();
+		assert_eq!(merged, expected);
+		assert_eq!(first.sequence_of(6), None);
+	}
+}
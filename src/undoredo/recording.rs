@@ -0,0 +1,289 @@
+//! An [`UndoRedo`] wrapper which records everything done through it.
+
+use core::ops::Deref;
+
+use bevy_ecs::{
+	system::{Commands, Resource},
+	world::World,
+};
+
+use super::{OperationSource, UndoRedo};
+use crate::{
+	error::Error,
+	operation::{Details, Operation, OperationContext},
+};
+
+/// Something that happened to an operation stored in a [`RecordingUndoRedo`].
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum HistoryEvent {
+	/// An operation was applied for the first time.
+	///
+	/// This holds the details of the history entry the operation was committed as. Where several
+	/// operations were committed as one [`OperationGroup`] (such as a run with the same [locality
+	/// key] applied by [`RecordingUndoRedo::apply_queue()`]), one event is recorded for the whole
+	/// group. Where an operation was merged into the previous entry instead, the operation's own
+	/// details are recorded.
+	///
+	/// [`OperationGroup`]: crate::common_operations::OperationGroup
+	/// [locality key]: Operation::locality_key()
+	Applied(Details),
+	/// An operation was undone.
+	Undone(Details),
+	/// An operation was redone.
+	Redone(Details),
+}
+
+/// A wrapper around an [`UndoRedo`], which records every operation applied, undone, or redone
+/// through it as a [`HistoryEvent`].
+///
+/// The recorded events can later be passed to [`RecordingUndoRedo::replay()`] to deterministically
+/// reproduce the same sequence of actions - for example, in a test reproducing a user-reported
+/// bug.
+///
+/// Read-only access to the wrapped `UndoRedo` is available through [`Deref`].
+#[derive(Default, Resource)]
+pub struct RecordingUndoRedo {
+	/// The wrapped `UndoRedo`.
+	undoredo: UndoRedo,
+	/// Every event recorded so far, from oldest to newest.
+	events: Vec<HistoryEvent>,
+}
+
+impl RecordingUndoRedo {
+	/// Creates a new `RecordingUndoRedo`, wrapping `undoredo`. Nothing that happened to `undoredo`
+	/// before this point is recorded.
+	#[must_use]
+	pub const fn new(undoredo: UndoRedo) -> Self {
+		Self {
+			undoredo,
+			events: Vec::new(),
+		}
+	}
+
+	/// Consumes this `RecordingUndoRedo`, returning the wrapped `UndoRedo`.
+	#[must_use]
+	pub fn into_inner(self) -> UndoRedo {
+		self.undoredo
+	}
+
+	/// Returns every event recorded so far, from oldest to newest.
+	#[must_use]
+	pub fn recorded_events(&self) -> &[HistoryEvent] {
+		&self.events
+	}
+
+	/// Clears all recorded events, without affecting the wrapped `UndoRedo`.
+	pub fn clear_recorded_events(&mut self) {
+		self.events.clear();
+	}
+
+	/// Replays `events` against `world` using `undoredo`, returning a new `RecordingUndoRedo`
+	/// wrapping it.
+	///
+	/// `undoredo` should be configured in the same way as the recorded `UndoRedo` was (such as by
+	/// building both from the same [`UndoRedoBuilder`]), and should hold the same operations as it
+	/// did when recording began. Otherwise, operations may be limited, merged, or grouped
+	/// differently than they were when recorded.
+	///
+	/// As events only contain an operation's [`Details`], `make_operation` is called for each
+	/// [`HistoryEvent::Applied`] event to recreate the operation that was committed - which, for a
+	/// group of operations, is the whole [`OperationGroup`].
+	///
+	/// # Errors
+	/// Returns the first error encountered while replaying. This usually means that `events` were
+	/// recorded from a different starting state than `world` is in.
+	///
+	/// [`UndoRedoBuilder`]: super::UndoRedoBuilder
+	/// [`OperationGroup`]: crate::common_operations::OperationGroup
+	pub fn replay(
+		events: &[HistoryEvent],
+		undoredo: UndoRedo,
+		world: &mut World,
+		mut make_operation: impl FnMut(&Details) -> Box<dyn Operation>,
+	) -> Result<Self, Error> {
+		let mut recording = Self::new(undoredo);
+
+		for event in events {
+			match event {
+				HistoryEvent::Applied(details) => {
+					let operation = make_operation(details);
+					super::with_world_commands(world, |commands| {
+						recording.apply_boxed(operation, commands)
+					})?;
+				}
+				HistoryEvent::Undone(_) => {
+					// The recorded undo happened, so it must already have been confirmed if needed.
+					super::with_world_commands(world, |commands| {
+						recording.undo_confirmed(commands)
+					})?;
+				}
+				HistoryEvent::Redone(_) => {
+					super::with_world_commands(world, |commands| recording.redo(commands))?;
+				}
+			}
+		}
+
+		Ok(recording)
+	}
+}
+
+/// Recorded versions of [`UndoRedo`]'s methods.
+impl RecordingUndoRedo {
+	/// See [`UndoRedo::push_to_queue()`]. Operations are recorded once they are applied.
+	///
+	/// # Errors
+	/// See [`UndoRedo::push_to_queue()`].
+	pub fn push_to_queue<O: Operation>(&mut self, operation: O) -> Result<(), Error> {
+		self.undoredo.push_to_queue(operation)
+	}
+
+	/// See [`UndoRedo::push_to_queue_from()`].
+	///
+	/// # Errors
+	/// See [`UndoRedo::push_to_queue_from()`].
+	pub fn push_to_queue_from<O: Operation>(
+		&mut self,
+		operation: O,
+		source: OperationSource,
+	) -> Result<(), Error> {
+		self.undoredo.push_to_queue_from(operation, source)
+	}
+
+	/// See [`UndoRedo::apply_queue()`].
+	///
+	/// # Errors
+	/// See [`UndoRedo::apply_queue()`].
+	pub fn apply_queue(&mut self, commands: &mut Commands) -> Result<(), Error> {
+		let newest_before = self.newest_sequence();
+		let (result, applied) = self
+			.undoredo
+			.apply_queue_reporting(|undoredo| undoredo.apply_queue(commands));
+		if !self.record_committed_since(newest_before) {
+			// Every applied operation was merged into an existing entry.
+			self.events
+				.extend(applied.into_iter().map(HistoryEvent::Applied));
+		}
+
+		result
+	}
+
+	/// See [`UndoRedo::push_and_apply()`].
+	///
+	/// # Errors
+	/// See [`UndoRedo::push_and_apply()`].
+	pub fn push_and_apply<O: Operation>(
+		&mut self,
+		operation: O,
+		commands: &mut Commands,
+	) -> Result<(), Error> {
+		self.apply_boxed(Box::new(operation), commands)
+	}
+
+	/// See [`UndoRedo::redo()`].
+	///
+	/// # Errors
+	/// See [`UndoRedo::redo()`].
+	pub fn redo(&mut self, commands: &mut Commands) -> Result<(), Error> {
+		let details = self
+			.undoredo
+			.history
+			.peek(1)
+			.map(|entry| entry.operation.details());
+		self.undoredo.redo(commands)?;
+		self.events.extend(details.map(HistoryEvent::Redone));
+		Ok(())
+	}
+
+	/// See [`UndoRedo::undo()`].
+	///
+	/// # Errors
+	/// See [`UndoRedo::undo()`].
+	pub fn undo(&mut self, commands: &mut Commands) -> Result<(), Error> {
+		self.undo_with(commands, UndoRedo::undo)
+	}
+
+	/// See [`UndoRedo::undo_confirmed()`].
+	///
+	/// # Errors
+	/// See [`UndoRedo::undo_confirmed()`].
+	pub fn undo_confirmed(&mut self, commands: &mut Commands) -> Result<(), Error> {
+		self.undo_with(commands, UndoRedo::undo_confirmed)
+	}
+}
+
+/// Private items.
+impl RecordingUndoRedo {
+	/// Undoes the last applied operation through `undo`, recording it if it succeeds.
+	fn undo_with(
+		&mut self,
+		commands: &mut Commands,
+		undo: fn(&mut UndoRedo, &mut Commands) -> Result<(), Error>,
+	) -> Result<(), Error> {
+		let details = self
+			.undoredo
+			.history
+			.peek(-1)
+			.map(|entry| entry.operation.details());
+		undo(&mut self.undoredo, commands)?;
+		self.events.extend(details.map(HistoryEvent::Undone));
+		Ok(())
+	}
+
+	/// Applies a boxed operation through the wrapped `UndoRedo`, recording it if it succeeds.
+	fn apply_boxed(
+		&mut self,
+		operation: Box<dyn Operation>,
+		commands: &mut Commands,
+	) -> Result<(), Error> {
+		let details = operation.details();
+		let newest_before = self.newest_sequence();
+		self.undoredo.apply_and_commit(
+			operation,
+			OperationSource::User,
+			commands,
+			&mut OperationContext::new(),
+		)?;
+		if !self.record_committed_since(newest_before) {
+			// The operation was merged into the previous entry, or cancelled it out.
+			self.events.push(HistoryEvent::Applied(details));
+		}
+		Ok(())
+	}
+
+	/// Returns the sequence number of the most recently applied operation, if any.
+	fn newest_sequence(&self) -> Option<u64> {
+		self.undoredo.history.peek(-1).map(|entry| entry.sequence)
+	}
+
+	/// Records a [`HistoryEvent::Applied`] event for each entry committed after the one with the
+	/// sequence number `newest_before`, returning `true` if there were any.
+	fn record_committed_since(&mut self, newest_before: Option<u64>) -> bool {
+		let mut committed: Vec<HistoryEvent> = self
+			.undoredo
+			.history
+			.iter_committed()
+			.rev()
+			.take_while(|entry| newest_before.is_none_or(|before| entry.sequence > before))
+			.map(|entry| HistoryEvent::Applied(entry.operation.details()))
+			.collect();
+		committed.reverse();
+
+		let any_committed = !committed.is_empty();
+		self.events.extend(committed);
+		any_committed
+	}
+}
+
+impl Deref for RecordingUndoRedo {
+	type Target = UndoRedo;
+
+	fn deref(&self) -> &Self::Target {
+		&self.undoredo
+	}
+}
+
+impl From<UndoRedo> for RecordingUndoRedo {
+	fn from(undoredo: UndoRedo) -> Self {
+		Self::new(undoredo)
+	}
+}
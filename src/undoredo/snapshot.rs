@@ -0,0 +1,78 @@
+//! An immutable snapshot of the operations stored in an [`UndoRedo`].
+//!
+//! [`UndoRedo`]: super::UndoRedo
+
+use crate::operation::Details;
+
+/// An immutable snapshot of the operations stored in an [`UndoRedo`], as returned by
+/// [`UndoRedo::snapshot_arc()`].
+///
+/// A snapshot does not contain the operations themselves - only their [`Details`] and
+/// [`OperationState`]s. As such, it is cheap to send to another thread (i.e. to be serialized in
+/// the background), and is unaffected by any later changes to the `UndoRedo`.
+///
+/// [`UndoRedo`]: super::UndoRedo
+/// [`UndoRedo::snapshot_arc()`]: super::UndoRedo::snapshot_arc()
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq)]
+pub struct HistorySnapshot {
+	/// The snapshotted operations, in the order described by [`Self::entries()`].
+	pub(super) entries: Vec<SnapshotEntry>,
+}
+
+impl HistorySnapshot {
+	/// Returns the snapshotted operations.
+	///
+	/// Applied operations come first, from oldest to newest. Then come undone operations, in the
+	/// order they would be redone. Finally, queued operations come last, in the order they would be
+	/// applied.
+	#[must_use]
+	pub fn entries(&self) -> &[SnapshotEntry] {
+		&self.entries
+	}
+}
+
+/// A single operation stored in a [`HistorySnapshot`].
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct SnapshotEntry {
+	/// The operation's details, at the time the snapshot was taken.
+	pub details: Details,
+	/// The operation's state, at the time the snapshot was taken.
+	pub state: OperationState,
+}
+
+/// A record of a single operation stored in an [`UndoRedo`], as returned by
+/// [`UndoRedo::export_audit()`].
+///
+/// Unlike a [`SnapshotEntry`], this only contains plain data, making it suitable for writing to a
+/// log file (i.e. to be attached to a bug report).
+///
+/// [`UndoRedo`]: super::UndoRedo
+/// [`UndoRedo::export_audit()`]: super::UndoRedo::export_audit()
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct AuditEntry {
+	/// The operation's name.
+	pub name: String,
+	/// The operation's state.
+	pub state: OperationState,
+	/// The sequence number given to the operation when it was applied, or `None` if it is queued
+	/// and has never been applied.
+	///
+	/// See [`UndoRedo::sequence_of()`](super::UndoRedo::sequence_of()) for more details.
+	pub sequence: Option<u64>,
+}
+
+/// The state of an operation stored in an [`UndoRedo`].
+///
+/// See [`UndoRedo`'s documentation](super::UndoRedo#operation-states) for more details on each
+/// state.
+///
+/// [`UndoRedo`]: super::UndoRedo
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OperationState {
+	/// The operation is waiting to be applied.
+	Queued,
+	/// The operation has been applied, and can be undone.
+	Applied,
+	/// The operation has been undone, and can be redone.
+	Undone,
+}